@@ -6985,6 +6985,10 @@ rec {
             name = "expect-test";
             packageId = "expect-test";
           }
+          {
+            name = "tempfile";
+            packageId = "tempfile";
+          }
         ];
         features = { };
         resolvedDefaultFeatures = [ "default" ];
@@ -7123,6 +7127,12 @@ rec {
             rename = "builtin-macros";
           }
         ];
+        buildDependencies = [
+          {
+            name = "sha2";
+            packageId = "sha2";
+          }
+        ];
         devDependencies = [
           {
             name = "criterion";
@@ -7327,15 +7337,31 @@ rec {
             name = "bytes";
             packageId = "bytes";
           }
+          {
+            name = "md-5";
+            packageId = "md-5";
+          }
           {
             name = "nix-compat";
             packageId = "nix-compat";
             usesDefaultFeatures = false;
           }
+          {
+            name = "serde_json";
+            packageId = "serde_json";
+          }
+          {
+            name = "sha1";
+            packageId = "sha1";
+          }
           {
             name = "sha2";
             packageId = "sha2";
           }
+          {
+            name = "toml";
+            packageId = "toml 0.6.0";
+          }
           {
             name = "tvix-eval";
             packageId = "tvix-eval";
@@ -7345,6 +7371,12 @@ rec {
             packageId = "walkdir";
           }
         ];
+        devDependencies = [
+          {
+            name = "tempfile";
+            packageId = "tempfile";
+          }
+        ];
 
       };
       "typenum" = rec {
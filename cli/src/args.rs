@@ -44,6 +44,16 @@ pub struct Args {
     #[clap(long)]
     pub no_warnings: bool,
 
+    /// Treat evaluation as failed if any warnings were emitted, even if
+    /// they weren't printed.
+    #[clap(long)]
+    pub fail_on_warnings: bool,
+
+    /// Only report warnings originating from the evaluated file or expression itself, not from
+    /// the files it imports.
+    #[clap(long)]
+    pub warnings_entry_only: bool,
+
     /// Additional entries to the Nix expression search path, a colon-separated list of directories
     /// used to resolve `<...>`-style lookup paths.
     ///
@@ -52,6 +62,12 @@ pub struct Args {
     #[clap(long = "extra-nix-path", short = 'I', action = clap::ArgAction::Append)]
     pub extra_nix_paths: Option<Vec<String>>,
 
+    /// An optional manifest mapping store paths to local directories, which reads from those
+    /// store paths are forwarded to. The manifest is a TOML table if its file name ends in
+    /// `.toml` and a JSON object otherwise.
+    #[clap(long)]
+    pub passthru_manifest: Option<PathBuf>,
+
     /// Print "raw" (unquoted) output.
     #[clap(long)]
     pub raw: bool,
@@ -72,6 +88,13 @@ pub struct Args {
     /// Tvix does not read from these.
     #[clap(long)]
     pub drv_dumpdir: Option<PathBuf>,
+
+    /// An optional directory in which the compiled bytecode of evaluated files is persisted, to
+    /// skip compilation when evaluating them again. If it doesn't exist, the directory is created.
+    ///
+    /// The directory must not be writable by untrusted users, as the bytecode in it is run.
+    #[clap(long, env = "TVIX_EVAL_CACHE_DIR")]
+    pub eval_cache_dir: Option<PathBuf>,
 }
 
 impl Args {
@@ -7,7 +7,7 @@ use smol_str::SmolStr;
 use std::fmt::Write;
 use tracing::instrument;
 use tvix_eval::{
-    ErrorKind, EvalIO, EvalMode, GlobalsMap, SourceCode, Value,
+    Diagnostic, Error, ErrorKind, EvalIO, EvalMode, EvalWarning, GlobalsMap, SourceCode, Value,
     builtins::impure_builtins,
     observer::{DisassemblingObserver, TracingObserver},
 };
@@ -30,12 +30,12 @@ pub fn init_io_handle(args: &Args) -> Rc<TvixStoreIO> {
     // TODO(tazjin): ugly for now, but this is temporary while we drop the old
     // store, this whole function will go away probably.
     let mut simstore = tvix_simstore::SimulatedStoreIO::default();
-    if let (Some(nix_path), Some(store_dir)) = (args.nix_path(), simstore.store_dir()) {
+    if let Some(nix_path) = args.nix_path() {
         let search_path =
             tvix_eval::NixSearchPath::from_str(&nix_path).expect("NIX_PATH was invalid");
         for entry in search_path.get_entries() {
             let path = entry.get_path();
-            if !path.starts_with(&store_dir) {
+            if !simstore.is_store_path(path) {
                 continue;
             }
 
@@ -45,6 +45,12 @@ pub fn init_io_handle(args: &Args) -> Rc<TvixStoreIO> {
         }
     }
 
+    if let Some(manifest) = &args.passthru_manifest {
+        simstore
+            .load_passthru_manifest(manifest)
+            .expect("loading passthru manifest failed");
+    }
+
     Rc::new(TvixStoreIO::new(simstore))
 }
 
@@ -67,9 +73,30 @@ pub struct IncompleteInput;
 pub struct EvalResult {
     value: Option<Value>,
     globals: Rc<GlobalsMap>,
+    warnings: Vec<EvalWarning>,
+    errors: Vec<Error>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl EvalResult {
+    /// Warnings emitted during evaluation, regardless of whether they were printed.
+    pub fn warnings(&self) -> &[EvalWarning] {
+        &self.warnings
+    }
+
+    /// Errors encountered during evaluation.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// Machine-readable descriptions of all errors, followed by all warnings.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
 }
 
-/// Interprets the given code snippet, printing out warnings and errors and returning the result
+/// Interprets the given code snippet, printing out warnings (unless disabled) and errors and
+/// returning the result, which carries them as well
 #[allow(clippy::too_many_arguments)]
 pub fn evaluate(
     tvix_store_io: Rc<TvixStoreIO>,
@@ -97,20 +124,24 @@ pub fn evaluate(
         }
         None => {
             eval_builder = eval_builder.add_builtins(impure_builtins());
-            eval_builder = eval_builder.add_builtins(simulated_store_builtins());
+            eval_builder = eval_builder
+                .add_builtins(simulated_store_builtins(
+                    Rc::clone(&tvix_store_io) as Rc<dyn EvalIO>
+                ));
             eval_builder = add_derivation_builtins(eval_builder, Rc::clone(&tvix_store_io));
             // eval_builder = add_fetcher_builtins(eval_builder, Rc::clone(&tvix_store_io));
             eval_builder = add_import_builtins(eval_builder, Rc::clone(&tvix_store_io));
         }
     };
     eval_builder = configure_nix_path(eval_builder, &args.nix_path());
+    eval_builder = eval_builder.eval_cache_dir(args.eval_cache_dir.clone());
 
     if let Some(source_map) = source_map {
         eval_builder = eval_builder.with_source_map(source_map);
     }
 
     let source_map = eval_builder.source_map().clone();
-    let (result, globals) = {
+    let (mut result, globals) = {
         let mut compiler_observer =
             DisassemblingObserver::new(source_map.clone(), std::io::stderr());
         if args.dump_bytecode {
@@ -143,6 +174,14 @@ pub fn evaluate(
         return Err(IncompleteInput);
     }
 
+    if args.warnings_entry_only {
+        if let Some(entry_span) = result.entry_span {
+            result
+                .warnings
+                .retain(|warning| entry_span.contains(warning.span));
+        }
+    }
+
     if args.display_ast {
         if let Some(ref expr) = result.expr {
             eprintln!("AST: {}", tvix_eval::pretty_print_expr(expr));
@@ -174,9 +213,19 @@ pub fn evaluate(
             })
     }
 
+    let diagnostics = result
+        .errors
+        .iter()
+        .map(Error::to_diagnostic)
+        .chain(result.warnings.iter().map(|w| w.to_diagnostic(&source_map)))
+        .collect();
+
     Ok(EvalResult {
         globals,
         value: result.value,
+        warnings: result.warnings,
+        errors: result.errors,
+        diagnostics,
     })
 }
 
@@ -184,6 +233,8 @@ pub struct InterpretResult {
     output: String,
     success: bool,
     pub(crate) globals: Option<Rc<GlobalsMap>>,
+    warnings: Vec<EvalWarning>,
+    errors: Vec<Error>,
 }
 
 impl InterpretResult {
@@ -192,6 +243,8 @@ impl InterpretResult {
             output: String::new(),
             success: true,
             globals,
+            warnings: vec![],
+            errors: vec![],
         }
     }
 
@@ -207,6 +260,16 @@ impl InterpretResult {
     pub fn success(&self) -> bool {
         self.success
     }
+
+    /// Warnings emitted during evaluation, regardless of whether they were printed.
+    pub fn warnings(&self) -> &[EvalWarning] {
+        &self.warnings
+    }
+
+    /// Errors encountered during evaluation.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
 }
 
 /// Interprets the given code snippet, printing out warnings, errors
@@ -247,10 +310,14 @@ pub fn interpret(
         }
     }
 
-    // inform the caller about any errors
+    // inform the caller about any errors, and warnings if requested
+    let success = result.value.is_some() && !(args.fail_on_warnings && !result.warnings.is_empty());
+
     Ok(InterpretResult {
         output,
-        success: result.value.is_some(),
+        success,
         globals: Some(result.globals),
+        warnings: result.warnings,
+        errors: result.errors,
     })
 }
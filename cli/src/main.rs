@@ -50,8 +50,8 @@ fn lint(code: &str, path: Option<PathBuf>, args: &Args) -> bool {
         warning.fancy_format_stderr(&source_map);
     }
 
-    // inform the caller about any errors
-    result.errors.is_empty()
+    // inform the caller about any errors, and warnings if requested
+    result.errors.is_empty() && !(args.fail_on_warnings && !result.warnings.is_empty())
 }
 
 fn main() {
@@ -26,6 +26,7 @@ pub(crate) enum ReplCommand<'a> {
     Assign(Assignment<'a>),
     Explain(&'a str),
     Print(&'a str),
+    Reload,
     Quit,
     Help,
 }
@@ -40,6 +41,7 @@ The following commands are supported:
   <x> = <expr> Bind the result of an expression to a variable
   :d <expr>    Evaluate a Nix language expression and print a detailed description of the result
   :p <expr>    Evaluate a Nix language expression and print the result recursively
+  :r, :reload  Re-read all files, re-evaluating the expressions of all bound variables
   :q           Exit the REPL
   :?, :h       Display this help text
 ";
@@ -54,6 +56,7 @@ The following commands are supported:
 
             let input = input.trim_end();
             match input {
+                ":r" | ":reload" => return Self::Reload,
                 ":q" => return Self::Quit,
                 ":h" | ":?" => return Self::Help,
                 _ => {}
@@ -90,6 +93,8 @@ pub struct Repl<'a> {
     rl: Editor<()>,
     /// Local variables defined at the top-level in the repl
     env: FxHashMap<SmolStr, Value>,
+    /// Source code of the bindings in `env`, in the order they were first defined, for reloading
+    bindings: Vec<(SmolStr, String)>,
 
     io_handle: Rc<TvixStoreIO>,
     args: &'a Args,
@@ -104,6 +109,7 @@ impl<'a> Repl<'a> {
             multiline_input: None,
             rl,
             env: FxHashMap::default(),
+            bindings: Vec::new(),
             io_handle,
             args,
             source_map: Default::default(),
@@ -157,6 +163,43 @@ impl<'a> Repl<'a> {
         }
     }
 
+    /// Discards the globals and all bound values, then re-evaluates the bindings in the order
+    /// they were defined, so that changes to the files they read are picked up. Returns the names
+    /// of the bindings that failed to re-evaluate and are now unbound.
+    fn reload(&mut self) -> Vec<SmolStr> {
+        self.globals = None;
+        self.source_map = Default::default();
+        self.env.clear();
+
+        let mut failed = vec![];
+        for (ident, code) in &self.bindings {
+            // Bindings are only recorded for complete input.
+            match evaluate(
+                Rc::clone(&self.io_handle),
+                code,
+                None,
+                self.args,
+                AllowIncomplete::Allow,
+                Some(&self.env),
+                self.globals.clone(),
+                Some(self.source_map.clone()),
+            ) {
+                Ok(result) => {
+                    match result.value {
+                        Some(value) => {
+                            self.env.insert(ident.clone(), value);
+                        }
+                        None => failed.push(ident.clone()),
+                    }
+                    self.globals = Some(result.globals);
+                }
+                Err(IncompleteInput) => failed.push(ident.clone()),
+            }
+        }
+
+        failed
+    }
+
     /// Send a line of user input to the REPL. Returns a result indicating the output to show to the
     /// user, and whether or not to continue
     pub fn send(&mut self, line: String) -> CommandResult {
@@ -198,9 +241,10 @@ impl<'a> Repl<'a> {
                 Some(self.source_map.clone()),
             ),
             ReplCommand::Assign(Assignment { ident, value }) => {
+                let code = value.to_string(); /* FIXME: don't re-parse */
                 match evaluate(
                     Rc::clone(&self.io_handle),
-                    &value.to_string(), /* FIXME: don't re-parse */
+                    &code,
                     None,
                     self.args,
                     AllowIncomplete::Allow,
@@ -211,12 +255,30 @@ impl<'a> Repl<'a> {
                     Ok(result) => {
                         if let Some(value) = result.value {
                             self.env.insert(ident.into(), value);
+
+                            // Rebinding a name replaces its earlier definition.
+                            match self
+                                .bindings
+                                .iter_mut()
+                                .find(|(name, _)| name.as_str() == ident)
+                            {
+                                Some((_, old_code)) => *old_code = code,
+                                None => self.bindings.push((ident.into(), code)),
+                            }
                         }
                         Ok(InterpretResult::empty_success(Some(result.globals)))
                     }
                     Err(incomplete) => Err(incomplete),
                 }
             }
+            ReplCommand::Reload => {
+                let failed = self.reload();
+                let mut result = InterpretResult::empty_success(self.globals.clone());
+                if !failed.is_empty() {
+                    result.output = format!("failed to re-evaluate: {}\n", failed.join(", "));
+                }
+                Ok(result)
+            }
             ReplCommand::Explain(input) => interpret(
                 Rc::clone(&self.io_handle),
                 input,
@@ -249,6 +311,7 @@ impl<'a> Repl<'a> {
                 output,
                 globals,
                 success: _,
+                ..
             }) => {
                 self.rl.add_history_entry(input);
                 self.multiline_input = None;
@@ -0,0 +1,145 @@
+use std::ffi::OsString;
+use std::process::Command;
+
+use clap::Parser;
+use tvix_cli::{AllowIncomplete, init_io_handle, interpret};
+use tvix_eval::WarningKind;
+
+#[test]
+fn interpret_returns_warnings() {
+    let args = tvix_cli::Args::parse_from(vec![
+        OsString::from("tvix"),
+        OsString::from("--no-warnings"),
+    ]);
+
+    let result = interpret(
+        init_io_handle(&args),
+        "let x = 1; in 2",
+        None,
+        &args,
+        false,
+        AllowIncomplete::RequireComplete,
+        None,
+        None,
+        None,
+    )
+    .expect("input must be complete");
+
+    assert!(result.success());
+    assert!(result.errors().is_empty());
+    assert!(
+        result
+            .warnings()
+            .iter()
+            .any(|w| matches!(w.kind, WarningKind::UnusedBinding)),
+        "expected an unused binding warning, got: {:?}",
+        result.warnings()
+    );
+}
+
+#[test]
+fn fail_on_warnings_exit_code() {
+    let run = |extra_args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_tvix"))
+            .arg("--no-warnings")
+            .args(extra_args)
+            .args(["-E", "let x = 1; in 2"])
+            .output()
+            .expect("must be able to run tvix")
+            .status
+    };
+
+    assert!(run(&[]).success());
+    assert!(!run(&["--fail-on-warnings"]).success());
+}
+
+#[test]
+fn eval_cache_dir_reused() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let script_dir = tempfile::tempdir().unwrap();
+    let script = script_dir.path().join("script.nix");
+    std::fs::write(&script, "let f = x: x * 2; in f 21").unwrap();
+
+    // Bytecode is only dumped when the script is actually compiled.
+    let run = || {
+        Command::new(env!("CARGO_BIN_EXE_tvix"))
+            .arg("--dump-bytecode")
+            .arg("--eval-cache-dir")
+            .arg(cache_dir.path())
+            .arg(&script)
+            .output()
+            .expect("must be able to run tvix")
+    };
+
+    let first = run();
+    assert!(first.status.success());
+    assert!(
+        !first.stderr.is_empty(),
+        "first run must compile the script"
+    );
+    assert_eq!(std::fs::read_dir(cache_dir.path()).unwrap().count(), 1);
+
+    let second = run();
+    assert!(second.status.success());
+    assert_eq!(first.stdout, second.stdout);
+    assert!(
+        second.stderr.is_empty(),
+        "second run must reuse the persisted bytecode, got: {}",
+        String::from_utf8_lossy(&second.stderr)
+    );
+}
+
+#[test]
+fn passthru_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/hello"), "hello").unwrap();
+
+    let store_path = "/nix/store/a396z42saqql55cp5n1vrb2j0siq86k1-src";
+    let manifest = dir.path().join("passthru.json");
+    std::fs::write(&manifest, format!(r#"{{ "{store_path}": "src" }}"#)).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tvix"))
+        .arg("--raw")
+        .arg("--passthru-manifest")
+        .arg(&manifest)
+        .args(["-E", &format!("builtins.readFile {store_path}/hello")])
+        .output()
+        .expect("must be able to run tvix");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(output.stdout, b"hello\n");
+}
+
+#[test]
+fn warnings_entry_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let main = dir.path().join("main.nix");
+    std::fs::write(&main, "import ./lib.nix").unwrap();
+    std::fs::write(dir.path().join("lib.nix"), "let unused = 1; in 2").unwrap();
+
+    let run = |extra_args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_tvix"))
+            .arg("--fail-on-warnings")
+            .args(extra_args)
+            .arg(&main)
+            .output()
+            .expect("must be able to run tvix")
+    };
+
+    let all = run(&[]);
+    assert!(!all.status.success());
+    assert!(String::from_utf8_lossy(&all.stderr).contains("W003"));
+
+    let entry_only = run(&["--warnings-entry-only"]);
+    assert!(
+        entry_only.status.success(),
+        "{}",
+        String::from_utf8_lossy(&entry_only.stderr)
+    );
+    assert!(!String::from_utf8_lossy(&entry_only.stderr).contains("W003"));
+}
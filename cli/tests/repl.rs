@@ -95,3 +95,31 @@ test_repl!(reference_nix_path() {
         => /tmp :: path
     "#]];
 });
+
+#[test]
+fn reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("value.nix");
+    std::fs::write(&file, "1").unwrap();
+
+    let args = tvix_cli::Args::parse_from(vec![OsString::from("tvix")]);
+    let mut repl = tvix_cli::Repl::new(init_io_handle(&args), &args);
+
+    let mut send = |line: String| repl.send(line).output().to_owned();
+    assert_eq!(send(format!("x = import {}", file.display())), "");
+    assert_eq!(send("x".into()), "=> 1 :: int\n");
+
+    std::fs::write(&file, "2").unwrap();
+    assert_eq!(send("x".into()), "=> 1 :: int\n");
+
+    assert_eq!(send(":reload".into()), "");
+    assert_eq!(send("x".into()), "=> 2 :: int\n");
+
+    std::fs::write(&file, "throw \"broken\"").unwrap();
+    assert_eq!(send(":reload".into()), "failed to re-evaluate: x\n");
+
+    // Only the latest definition of a rebound name is re-evaluated.
+    assert_eq!(send("x = 3".into()), "");
+    assert_eq!(send(":reload".into()), "");
+    assert_eq!(send("x".into()), "=> 3 :: int\n");
+}
@@ -1,4 +1,8 @@
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
 
 fn main() {
     println!(
@@ -7,8 +11,69 @@ fn main() {
     );
     println!("cargo:rerun-if-changed-env=TARGET");
 
+    // Bytecode persisted by one build of the compiler must not be
+    // loaded by another, so the persistent compilation cache is keyed
+    // on a fingerprint of the sources and dependency versions.
+    println!(
+        "cargo:rustc-env=TVIX_EVAL_SOURCE_FINGERPRINT={}",
+        source_fingerprint()
+    );
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=builtin-macros/src");
+
     // Pick up new test case files
     // https://github.com/la10736/rstest/issues/256
     println!("cargo:rerun-if-changed=src/tests/nix_tests");
     println!("cargo:rerun-if-changed=src/tests/tvix_tests")
 }
+
+/// Hashes all source files of this crate and its builtin macros,
+/// except for the tests, along with the dependency versions pinned in
+/// the lock file of the enclosing workspace, if there is one.
+fn source_fingerprint() -> String {
+    fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                if path != Path::new("src/tests") {
+                    collect(&path, files);
+                }
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    let mut files = vec![];
+    collect(Path::new("src"), &mut files);
+    collect(Path::new("builtin-macros/src"), &mut files);
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    let mut update = |bytes: &[u8]| {
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    };
+
+    update(env!("CARGO_PKG_VERSION").as_bytes());
+    for file in files {
+        update(file.as_os_str().as_encoded_bytes());
+        update(&fs::read(&file).unwrap());
+    }
+
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    if let Some(lock_file) = manifest_dir
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file())
+    {
+        println!("cargo:rerun-if-changed={}", lock_file.display());
+        update(&fs::read(&lock_file).unwrap());
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
@@ -81,6 +81,34 @@ pub async fn coerce_value_to_path(
     }
 }
 
+/// Convert a parsed TOML value to the equivalent Nix value. Date-times
+/// are represented as strings in their TOML syntax (C++ Nix rejects
+/// them, unless the `parse-toml-timestamps` feature is enabled).
+fn toml_to_value(toml: toml::Value) -> Value {
+    match toml {
+        toml::Value::String(s) => s.into(),
+        toml::Value::Integer(i) => i.into(),
+        toml::Value::Float(f) => f.into(),
+        toml::Value::Boolean(b) => b.into(),
+        toml::Value::Datetime(dt) => dt.to_string().into(),
+        toml::Value::Array(array) => Value::List(
+            array
+                .into_iter()
+                .map(toml_to_value)
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+        // Attribute sets are sorted by key, independent of the order
+        // of the keys in the TOML source.
+        toml::Value::Table(table) => Value::attrs(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_value(value)))
+                .collect(),
+        ),
+    }
+}
+
 static REGEX_CACHE: OnceLock<Mutex<FxHashMap<String, Regex>>> = OnceLock::new();
 
 fn cached_regex(pattern: &str) -> Result<Regex, regex::Error> {
@@ -477,8 +505,9 @@ mod pure_builtins {
     #[builtin("fromTOML")]
     async fn builtin_from_toml(co: GenCo, toml: Value) -> Result<Value, ErrorKind> {
         let toml_str = toml.to_str()?;
+        let table: toml::Table = toml::from_str(toml_str.to_str()?)?;
 
-        toml::from_str(toml_str.to_str()?).map_err(|err| err.into())
+        Ok(toml_to_value(toml::Value::Table(table)))
     }
 
     #[builtin("genericClosure")]
@@ -2,6 +2,7 @@ use crate::opcode::{CodeIdx, ConstantIdx, Op, OpArg};
 use crate::value::Value;
 use crate::{CoercionKind, SourceCode};
 use integer_encoding::VarInt;
+use rustc_hash::FxHashMap;
 use std::io::Write;
 
 /// Represents a source location from which one or more operations
@@ -24,6 +25,33 @@ struct SourceSpan {
     start: usize,
 }
 
+/// Identity of a constant which is cheap to compare, used for
+/// deduplicating constants within a [Chunk].
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum ConstantKey {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(u64),
+    String(Box<[u8]>),
+}
+
+impl ConstantKey {
+    /// Returns the key of the given value, if it is eligible for
+    /// deduplication. Values with identity (thunks, closures, ...) and
+    /// strings with context never are.
+    fn for_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(Self::Null),
+            Value::Bool(b) => Some(Self::Bool(*b)),
+            Value::Integer(i) => Some(Self::Integer(*i)),
+            Value::Float(f) => Some(Self::Float(f.to_bits())),
+            Value::String(s) if !s.has_context() => Some(Self::String(s.as_bytes().into())),
+            _ => None,
+        }
+    }
+}
+
 /// A chunk is a representation of a sequence of bytecode
 /// instructions, associated constants and additional metadata as
 /// emitted by the compiler.
@@ -33,6 +61,9 @@ pub struct Chunk {
     pub constants: Vec<Value>,
     spans: Vec<SourceSpan>,
 
+    /// Indices of constants which can be shared by identical values.
+    constant_indices: FxHashMap<ConstantKey, ConstantIdx>,
+
     /// Index of the last operation (i.e. not data) written to the code vector.
     /// Some operations (e.g. jump patching) need to know this.
     last_op: usize,
@@ -85,6 +116,30 @@ impl Chunk {
         u16::from_le_bytes(*byte_array)
     }
 
+    /// Reassemble a chunk from its code, constants and source spans,
+    /// e.g. when loading it from a persistent cache.
+    pub(crate) fn from_parts(
+        code: Vec<u8>,
+        constants: Vec<Value>,
+        spans: Vec<(usize, codemap::Span)>,
+    ) -> Self {
+        Chunk {
+            code,
+            constants,
+            spans: spans
+                .into_iter()
+                .map(|(start, span)| SourceSpan { span, start })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Source spans of the chunk, as pairs of the index of the first
+    /// operation compiled from each span and the span itself.
+    pub(crate) fn spans(&self) -> impl Iterator<Item = (usize, codemap::Span)> + '_ {
+        self.spans.iter().map(|s| (s.start, s.span))
+    }
+
     /// Get the first span of a chunk, no questions asked.
     pub fn first_span(&self) -> codemap::Span {
         self.spans[0].span
@@ -99,10 +154,22 @@ impl Chunk {
         Some((self.code[self.last_op].into(), self.last_op))
     }
 
+    /// Add a constant to the chunk and return its index. Identical
+    /// constants of simple types (see [ConstantKey]) share an index.
     pub fn push_constant(&mut self, data: Value) -> ConstantIdx {
-        let idx = self.constants.len();
+        let key = ConstantKey::for_value(&data);
+        if let Some(idx) = key.as_ref().and_then(|k| self.constant_indices.get(k)) {
+            return *idx;
+        }
+
+        let idx = ConstantIdx(self.constants.len());
         self.constants.push(data);
-        ConstantIdx(idx)
+
+        if let Some(key) = key {
+            self.constant_indices.insert(key, idx);
+        }
+
+        idx
     }
 
     /// Return a reference to the constant at the given [`ConstantIdx`]
@@ -286,4 +353,21 @@ mod tests {
 
         assert_eq!(chunk.code, expected);
     }
+
+    #[test]
+    fn push_constant_dedup() {
+        let mut chunk = Chunk::default();
+
+        let foo = chunk.push_constant(Value::from("foo"));
+        let one = chunk.push_constant(Value::Integer(1));
+        assert_eq!(foo, chunk.push_constant(Value::from("foo")));
+        assert_eq!(one, chunk.push_constant(Value::Integer(1)));
+        assert_ne!(foo, chunk.push_constant(Value::from("bar")));
+
+        // Values with identity are never shared.
+        let list = chunk.push_constant(Value::List(vec![].into()));
+        assert_ne!(list, chunk.push_constant(Value::List(vec![].into())));
+
+        assert_eq!(chunk.constants.len(), 5);
+    }
 }
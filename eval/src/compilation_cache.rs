@@ -0,0 +1,247 @@
+//! This module implements an opt-in cache for compiled top-level
+//! expressions, which lets repeated evaluations of unchanged source
+//! code (e.g. in a watch mode) skip parsing and compilation.
+//!
+//! Compiled expressions can additionally be persisted on disk, see the
+//! [`persistent`] module.
+
+pub(crate) mod persistent;
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use sha2::{Digest, Sha256};
+
+use crate::chunk::Chunk;
+use crate::compiler::GlobalsMap;
+use crate::source::SourceCode;
+use crate::value::{Closure, Lambda, Thunk, ThunkSet, Value};
+use crate::warnings::EvalWarning;
+
+/// Identifies a piece of source code evaluated at a given location,
+/// along with the compiler settings affecting its bytecode.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey {
+    location: Option<PathBuf>,
+    digest: [u8; 32],
+    import_interpolated_paths: bool,
+}
+
+impl CacheKey {
+    pub(crate) fn new(
+        code: &str,
+        location: Option<&Path>,
+        import_interpolated_paths: bool,
+    ) -> Self {
+        Self {
+            location: location.map(Path::to_path_buf),
+            digest: Sha256::digest(code).into(),
+            import_interpolated_paths,
+        }
+    }
+}
+
+/// Output of a previous successful compilation.
+#[derive(Clone)]
+pub(crate) struct CachedCompilation {
+    pub file: Arc<codemap::File>,
+    pub lambda: Rc<Lambda>,
+    pub warnings: Vec<EvalWarning>,
+    pub expr: Option<rnix::ast::Expr>,
+}
+
+impl CachedCompilation {
+    /// Returns a copy of this compilation whose bytecode can be run
+    /// without affecting other evaluations, see [`fresh_lambda`].
+    fn fresh(&self, globals: &SharedGlobals) -> Self {
+        Self {
+            lambda: fresh_lambda(&self.lambda, globals).unwrap_or_else(|| self.lambda.clone()),
+            ..self.clone()
+        }
+    }
+}
+
+/// Globals that may appear among the constants rebuilt by
+/// [`fresh_lambda`], which must be shared rather than copied.
+#[derive(Default)]
+struct SharedGlobals {
+    thunks: ThunkSet,
+    closures: FxHashSet<*const Closure>,
+}
+
+impl SharedGlobals {
+    fn new(globals: &GlobalsMap) -> Self {
+        let mut shared = Self::default();
+        for global in globals.values() {
+            match global {
+                Value::Thunk(thunk) => {
+                    shared.thunks.insert(thunk);
+                }
+                Value::Closure(closure) => {
+                    shared.closures.insert(Rc::as_ptr(closure));
+                }
+                _ => {}
+            }
+        }
+
+        shared
+    }
+}
+
+/// Returns a copy of the given lambda with fresh copies of the
+/// suspended thunks in its constants, and in those of all nested
+/// lambdas, or [None] if it has no such constants.
+///
+/// The compiler emits code without free variables as suspended thunk
+/// constants, which stay forced once they have been evaluated. Values
+/// they depend on (e.g. imported files) may differ between
+/// evaluations, so each evaluation must run its own copy of them.
+/// Constants referring to globals are shared, as are all other
+/// constants, which are immutable. Lambdas without thunks among their
+/// (nested) constants are shared as well.
+fn fresh_lambda(lambda: &Lambda, globals: &SharedGlobals) -> Option<Rc<Lambda>> {
+    let mut constants: Option<Vec<Value>> = None;
+    for (idx, constant) in lambda.chunk.constants.iter().enumerate() {
+        if let Some(fresh) = fresh_constant(constant, globals) {
+            constants.get_or_insert_with(|| lambda.chunk.constants.clone())[idx] = fresh;
+        }
+    }
+
+    let constants = constants?;
+    Some(Rc::new(Lambda {
+        chunk: Chunk::from_parts(
+            lambda.chunk.code.clone(),
+            constants,
+            lambda.chunk.spans().collect(),
+        ),
+        name: lambda.name.clone(),
+        upvalue_count: lambda.upvalue_count,
+        formals: lambda.formals.clone(),
+    }))
+}
+
+/// Returns a fresh copy of the given constant, if it is or contains a
+/// suspended thunk, see [`fresh_lambda`].
+fn fresh_constant(constant: &Value, globals: &SharedGlobals) -> Option<Value> {
+    match constant {
+        Value::Blueprint(lambda) => fresh_lambda(lambda, globals).map(Value::Blueprint),
+
+        Value::Closure(closure)
+            if closure.lambda.upvalue_count == 0
+                && !globals.closures.contains(&Rc::as_ptr(closure)) =>
+        {
+            let lambda = fresh_lambda(&closure.lambda, globals)?;
+            Some(Value::Closure(Rc::new(Closure::new(lambda))))
+        }
+
+        Value::Thunk(thunk) if !globals.thunks.contains(thunk) => {
+            let (lambda, span) = thunk.suspended_lambda()?;
+            let lambda = fresh_lambda(&lambda, globals).unwrap_or(lambda);
+            Some(Value::Thunk(Thunk::new_suspended(lambda, span)))
+        }
+
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct CacheState {
+    /// Globals and source map that all cached entries were compiled
+    /// against. Bytecode refers to both, so entries are discarded as
+    /// soon as either of them changes.
+    context: Option<(Rc<GlobalsMap>, SourceCode)>,
+    /// Globals of the context that entries share, see [`fresh_lambda`].
+    shared_globals: SharedGlobals,
+    entries: FxHashMap<CacheKey, CachedCompilation>,
+}
+
+impl CacheState {
+    fn matches(&self, globals: &Rc<GlobalsMap>, source: &SourceCode) -> bool {
+        match &self.context {
+            Some((cached_globals, cached_source)) => {
+                Rc::ptr_eq(cached_globals, globals) && cached_source.ptr_eq(source)
+            }
+            None => false,
+        }
+    }
+}
+
+/// Cache of compiled top-level expressions, keyed by the SHA-256 of
+/// their source code and their location.
+///
+/// The cache is only consulted for evaluations sharing the same
+/// globals and source map (see [`EvaluationBuilder::with_globals`]
+/// and [`EvaluationBuilder::with_source_map`]); any other evaluation
+/// invalidates all existing entries.
+///
+/// Each evaluation runs its own copy of the cached bytecode, so values
+/// of constant sub-expressions (e.g. imports) are not shared between
+/// evaluations.
+///
+/// [`EvaluationBuilder::with_globals`]: crate::EvaluationBuilder::with_globals
+/// [`EvaluationBuilder::with_source_map`]: crate::EvaluationBuilder::with_source_map
+#[derive(Default)]
+pub struct CompilationCache(RefCell<CacheState>);
+
+impl CompilationCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of compiled expressions currently held by the cache.
+    pub fn len(&self) -> usize {
+        self.0.borrow().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Discard all cached compilation results.
+    pub fn clear(&self) {
+        let mut state = self.0.borrow_mut();
+        state.context = None;
+        state.shared_globals = SharedGlobals::default();
+        state.entries.clear();
+    }
+
+    pub(crate) fn get(
+        &self,
+        key: &CacheKey,
+        globals: &Rc<GlobalsMap>,
+        source: &SourceCode,
+    ) -> Option<CachedCompilation> {
+        let state = self.0.borrow();
+        if !state.matches(globals, source) {
+            return None;
+        }
+
+        state
+            .entries
+            .get(key)
+            .map(|compilation| compilation.fresh(&state.shared_globals))
+    }
+
+    pub(crate) fn insert(
+        &self,
+        key: CacheKey,
+        globals: &Rc<GlobalsMap>,
+        source: &SourceCode,
+        compilation: CachedCompilation,
+    ) {
+        let mut state = self.0.borrow_mut();
+        if !state.matches(globals, source) {
+            state.context = Some((globals.clone(), source.clone()));
+            state.shared_globals = SharedGlobals::new(globals);
+            state.entries.clear();
+        }
+
+        // The inserted bytecode is about to be run by the current
+        // evaluation, so the cache keeps a copy of it.
+        let compilation = compilation.fresh(&state.shared_globals);
+        state.entries.insert(key, compilation);
+    }
+}
@@ -0,0 +1,734 @@
+//! Persistent counterpart of the in-memory [`CompilationCache`], which
+//! stores compiled top-level expressions in a directory, so that they
+//! can be reused across processes.
+//!
+//! Entries are keyed by the SHA-256 of the source code, its location,
+//! the compiler settings, the names of all globals and a fingerprint of
+//! the sources of this crate, so that bytecode is never loaded by a
+//! different build of the compiler or VM. Loaded bytecode is validated
+//! before use, and invalid entries are ignored. Validation is not
+//! complete (see `validate_code`), so the cache directory must only
+//! be writable by users trusted to run arbitrary code. Constants
+//! referring to globals are stored by name and resolved against the
+//! globals of the loading evaluation, spans are stored relative to the
+//! start of the compiled file.
+//!
+//! Only expressions compiled without warnings are persisted, and only if
+//! all of their constants can be represented on disk.
+//!
+//! [`CompilationCache`]: super::CompilationCache
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use bstr::ByteSlice;
+use codemap::Span;
+use data_encoding::HEXLOWER;
+use integer_encoding::VarInt;
+use os_str_bytes::{OsStrBytes, OsStringBytes};
+use rowan::ast::AstNode;
+use sha2::{Digest, Sha256};
+use smol_str::SmolStr;
+
+use crate::chunk::Chunk;
+use crate::compiler::{GlobalsMap, expr_static_attr_str};
+use crate::opcode::{Op, OpArg, Position};
+use crate::value::{Closure, Formals, Lambda, NixAttrs, NixString, Thunk, Value};
+
+/// Version of the on-disk format, which must be bumped whenever the
+/// encoding changes incompatibly. Changes to the bytecode itself are
+/// covered by the source fingerprint.
+const FORMAT_VERSION: u64 = 2;
+
+/// Prefix of all cache files.
+const MAGIC: &[u8] = b"tvix-bytecode\0";
+
+/// Length of the SHA-256 digest of the payload, stored at the end of
+/// each cache file to detect truncated or otherwise corrupt entries.
+const DIGEST_LEN: usize = 32;
+
+// Tags of the constants that can be persisted.
+const TAG_NULL: u8 = 0;
+const TAG_BOOL: u8 = 1;
+const TAG_INTEGER: u8 = 2;
+const TAG_FLOAT: u8 = 3;
+const TAG_STRING: u8 = 4;
+const TAG_PATH: u8 = 5;
+const TAG_UNRESOLVED_PATH: u8 = 6;
+const TAG_FINALISE_REQUEST: u8 = 7;
+const TAG_EMPTY_ATTRS: u8 = 8;
+const TAG_ATTR_NOT_FOUND: u8 = 9;
+const TAG_BLUEPRINT: u8 = 10;
+const TAG_CLOSURE: u8 = 11;
+const TAG_THUNK: u8 = 12;
+const TAG_GLOBAL: u8 = 13;
+
+/// Identifies a piece of source code compiled at a given location,
+/// along with everything else affecting its bytecode.
+pub(crate) struct PersistentKey([u8; 32]);
+
+impl PersistentKey {
+    pub(crate) fn new(
+        code: &str,
+        location: &Path,
+        import_interpolated_paths: bool,
+        globals: &GlobalsMap,
+    ) -> Self {
+        let mut hasher = Sha256::new();
+        let mut update = |bytes: &[u8]| {
+            hasher.update((bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        };
+
+        update(&FORMAT_VERSION.to_le_bytes());
+        update(env!("TVIX_EVAL_SOURCE_FINGERPRINT").as_bytes());
+        update(&location.as_os_str().to_raw_bytes());
+        update(&[import_interpolated_paths as u8]);
+
+        // Which identifiers are globals determines the emitted bytecode.
+        let mut names: Vec<&str> = globals.keys().copied().collect();
+        names.sort_unstable();
+        for name in names {
+            update(name.as_bytes());
+        }
+
+        update(code.as_bytes());
+
+        Self(hasher.finalize().into())
+    }
+
+    fn path(&self, dir: &Path) -> PathBuf {
+        dir.join(HEXLOWER.encode(&self.0))
+    }
+}
+
+/// Load the compiled top-level lambda stored under the given key, with
+/// all spans pointing into the given file. Missing, corrupt or outdated
+/// entries are ignored.
+pub(crate) fn load(
+    dir: &Path,
+    key: &PersistentKey,
+    globals: &GlobalsMap,
+    file: &codemap::File,
+) -> Option<Rc<Lambda>> {
+    let data = std::fs::read(key.path(dir)).ok()?;
+    let data = data.strip_prefix(MAGIC)?;
+    let (payload, digest) = data.split_at_checked(data.len().checked_sub(DIGEST_LEN)?)?;
+
+    if Sha256::digest(payload).as_slice() != digest {
+        return None;
+    }
+
+    let mut decoder = Decoder {
+        globals,
+        file,
+        data: payload,
+    };
+
+    // Top-level code is run without upvalues or arguments.
+    let lambda = decoder.lambda()?;
+    if !decoder.data.is_empty() || lambda.upvalue_count != 0 || lambda.formals.is_some() {
+        return None;
+    }
+
+    Some(Rc::new(lambda))
+}
+
+/// Store the compiled top-level lambda of the given expression under
+/// the given key, if it can be persisted.
+pub(crate) fn store(
+    dir: &Path,
+    key: &PersistentKey,
+    globals: &GlobalsMap,
+    file: &codemap::File,
+    expr: &rnix::ast::Expr,
+    lambda: &Lambda,
+) -> std::io::Result<()> {
+    if references_volatile_global(expr, globals) {
+        return Ok(());
+    }
+
+    let mut encoder = Encoder {
+        globals,
+        file,
+        data: MAGIC.to_vec(),
+    };
+
+    if encoder.lambda(lambda).is_none() {
+        return Ok(());
+    }
+
+    let digest = Sha256::digest(&encoder.data[MAGIC.len()..]);
+    encoder.data.extend_from_slice(&digest);
+
+    // Write to a temporary file first, so that concurrent evaluations
+    // never observe partially written entries.
+    std::fs::create_dir_all(dir)?;
+    let path = key.path(dir);
+    let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+    let result = std::fs::File::create(&tmp_path)
+        .and_then(|mut file| file.write_all(&encoder.data))
+        .and_then(|()| std::fs::rename(&tmp_path, path));
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+
+    result
+}
+
+/// Globals with primitive values are emitted as plain constants, which
+/// can not be told apart from literals in the bytecode. Their values may
+/// differ between evaluations (e.g. `__currentTime`), so code referring
+/// to them is not persisted.
+///
+/// The same applies to primitive attributes of globals, which the
+/// compiler inlines when they are selected statically (e.g.
+/// `builtins.currentTime`).
+fn references_volatile_global(expr: &rnix::ast::Expr, globals: &GlobalsMap) -> bool {
+    expr.syntax()
+        .descendants()
+        .filter_map(rnix::ast::Expr::cast)
+        .any(|expr| {
+            matches!(
+                static_global_value(&expr, globals),
+                Some(Value::Integer(_) | Value::Float(_) | Value::String(_) | Value::Path(_))
+            )
+        })
+}
+
+/// Returns the value the compiler emits as a constant for the given
+/// expression, if it refers to a global or to a statically selected
+/// attribute of one. Mirrors `Compiler::optimise_select`.
+fn static_global_value<'a>(expr: &rnix::ast::Expr, globals: &'a GlobalsMap) -> Option<&'a Value> {
+    match expr {
+        rnix::ast::Expr::Ident(ident) => globals.get(ident.ident_token()?.text()),
+        rnix::ast::Expr::Paren(paren) => static_global_value(&paren.expr()?, globals),
+        rnix::ast::Expr::Select(select) => {
+            let Value::Attrs(attrs) = static_global_value(&select.expr()?, globals)? else {
+                return None;
+            };
+
+            let mut path = select.attrpath()?.attrs();
+            match (path.next(), path.next()) {
+                (Some(attr), None) => attrs.select(expr_static_attr_str(&attr)?.as_bytes()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns the name of the global the given constant was emitted for,
+/// if any.
+fn global_name(globals: &GlobalsMap, value: &Value) -> Option<&'static str> {
+    globals
+        .iter()
+        .find(|(_, global)| match (global, value) {
+            (Value::Attrs(a), Value::Attrs(b)) => a.ptr_eq(b),
+            (Value::List(a), Value::List(b)) => a.ptr_eq(b),
+            (Value::Thunk(a), Value::Thunk(b)) => a.ptr_eq(b),
+            (Value::Closure(a), Value::Closure(b)) => Rc::ptr_eq(a, b),
+            (Value::Builtin(a), Value::Builtin(b)) => a.name() == b.name(),
+            _ => false,
+        })
+        .map(|(name, _)| *name)
+}
+
+/// Encodes lambdas into the on-disk format. All methods return [None]
+/// if the lambda can not be persisted.
+struct Encoder<'a> {
+    globals: &'a GlobalsMap,
+    file: &'a codemap::File,
+    data: Vec<u8>,
+}
+
+impl Encoder<'_> {
+    fn uvarint(&mut self, n: u64) {
+        self.data.extend_from_slice(&n.encode_var_vec());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.uvarint(bytes.len() as u64);
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn optional_bytes(&mut self, bytes: Option<&[u8]>) {
+        match bytes {
+            Some(bytes) => {
+                self.data.push(1);
+                self.bytes(bytes);
+            }
+            None => self.data.push(0),
+        }
+    }
+
+    fn span(&mut self, span: Span) -> Option<()> {
+        let file_span = self.file.span;
+        if span.low() < file_span.low() || span.high() > file_span.high() {
+            return None;
+        }
+
+        self.uvarint(span.low() - file_span.low());
+        self.uvarint(span.high() - file_span.low());
+        Some(())
+    }
+
+    fn lambda(&mut self, lambda: &Lambda) -> Option<()> {
+        self.optional_bytes(lambda.name.as_ref().map(|name| name.as_bytes()));
+        self.uvarint(lambda.upvalue_count as u64);
+
+        match &lambda.formals {
+            Some(formals) => {
+                self.data.push(1);
+                self.uvarint(formals.arguments.len() as u64);
+                for (name, required) in &formals.arguments {
+                    self.bytes(name.as_bytes());
+                    self.data.push(*required as u8);
+                }
+                self.data.push(formals.ellipsis as u8);
+                self.span(formals.span)?;
+                self.optional_bytes(formals.name.as_ref().map(|name| name.as_bytes()));
+            }
+            None => self.data.push(0),
+        }
+
+        self.chunk(&lambda.chunk)
+    }
+
+    fn chunk(&mut self, chunk: &Chunk) -> Option<()> {
+        self.bytes(&chunk.code);
+
+        self.uvarint(chunk.constants.len() as u64);
+        for constant in &chunk.constants {
+            self.constant(constant)?;
+        }
+
+        let spans: Vec<_> = chunk.spans().collect();
+        self.uvarint(spans.len() as u64);
+        for (start, span) in spans {
+            self.uvarint(start as u64);
+            self.span(span)?;
+        }
+
+        Some(())
+    }
+
+    fn constant(&mut self, value: &Value) -> Option<()> {
+        if let Some(name) = global_name(self.globals, value) {
+            self.data.push(TAG_GLOBAL);
+            self.bytes(name.as_bytes());
+            return Some(());
+        }
+
+        match value {
+            Value::Null => self.data.push(TAG_NULL),
+            Value::Bool(b) => self.data.extend_from_slice(&[TAG_BOOL, *b as u8]),
+            Value::Integer(i) => {
+                self.data.push(TAG_INTEGER);
+                self.data.extend_from_slice(&i.encode_var_vec());
+            }
+            Value::Float(f) => {
+                self.data.push(TAG_FLOAT);
+                self.data.extend_from_slice(&f.to_bits().to_le_bytes());
+            }
+            Value::String(s) if !s.has_context() => {
+                self.data.push(TAG_STRING);
+                self.bytes(s.as_bytes());
+            }
+            Value::Path(p) => {
+                self.data.push(TAG_PATH);
+                self.bytes(&p.as_os_str().to_raw_bytes());
+            }
+            Value::UnresolvedPath(p) => {
+                self.data.push(TAG_UNRESOLVED_PATH);
+                self.bytes(&p.as_os_str().to_raw_bytes());
+            }
+            Value::FinaliseRequest(b) => self
+                .data
+                .extend_from_slice(&[TAG_FINALISE_REQUEST, *b as u8]),
+            Value::Attrs(attrs) if attrs.is_empty() => self.data.push(TAG_EMPTY_ATTRS),
+            Value::AttrNotFound => self.data.push(TAG_ATTR_NOT_FOUND),
+            Value::Blueprint(lambda) => {
+                self.data.push(TAG_BLUEPRINT);
+                self.lambda(lambda)?;
+            }
+            Value::Closure(closure) if closure.lambda.upvalue_count == 0 => {
+                self.data.push(TAG_CLOSURE);
+                self.lambda(&closure.lambda)?;
+            }
+            Value::Thunk(thunk) => {
+                let (lambda, span) = thunk.suspended_lambda()?;
+                if lambda.upvalue_count != 0 {
+                    return None;
+                }
+
+                self.data.push(TAG_THUNK);
+                self.lambda(&lambda)?;
+                self.span(span)?;
+            }
+            _ => return None,
+        }
+
+        Some(())
+    }
+}
+
+/// Decodes lambdas from the on-disk format. All methods return [None]
+/// on invalid input.
+struct Decoder<'a> {
+    globals: &'a GlobalsMap,
+    file: &'a codemap::File,
+    data: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let (byte, rest) = self.data.split_first()?;
+        self.data = rest;
+        Some(*byte)
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        match self.byte()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    fn uvarint(&mut self) -> Option<u64> {
+        let (n, len) = u64::decode_var(self.data)?;
+        self.data = &self.data[len..];
+        Some(n)
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        self.uvarint()?.try_into().ok()
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.len()?;
+        let (bytes, rest) = self.data.split_at_checked(len)?;
+        self.data = rest;
+        Some(bytes)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        String::from_utf8(self.bytes()?.to_vec()).ok()
+    }
+
+    fn optional_string(&mut self) -> Option<Option<String>> {
+        if self.bool()? {
+            self.string().map(Some)
+        } else {
+            Some(None)
+        }
+    }
+
+    fn path(&mut self) -> Option<Box<PathBuf>> {
+        let bytes = self.bytes()?.to_vec();
+        Some(Box::new(OsString::assert_from_raw_vec(bytes).into()))
+    }
+
+    fn span(&mut self) -> Option<Span> {
+        let low = self.uvarint()?;
+        let high = self.uvarint()?;
+        if low > high || high > self.file.span.len() {
+            return None;
+        }
+
+        Some(self.file.span.subspan(low, high))
+    }
+
+    fn lambda(&mut self) -> Option<Lambda> {
+        let name = self.optional_string()?.map(SmolStr::from);
+        let upvalue_count = self.len()?;
+
+        let formals = if self.bool()? {
+            let count = self.len()?;
+            let mut arguments: BTreeMap<NixString, bool> = BTreeMap::new();
+            for _ in 0..count {
+                let name = self.bytes()?;
+                arguments.insert(name.into(), self.bool()?);
+            }
+
+            Some(Formals {
+                arguments,
+                ellipsis: self.bool()?,
+                span: self.span()?,
+                name: self.optional_string()?,
+            })
+        } else {
+            None
+        };
+
+        let chunk = self.chunk()?;
+        validate_code(&chunk, upvalue_count, formals.is_some())?;
+
+        Some(Lambda {
+            chunk,
+            name,
+            upvalue_count,
+            formals,
+        })
+    }
+
+    fn chunk(&mut self) -> Option<Chunk> {
+        let code = self.bytes()?.to_vec();
+
+        let mut constants = vec![];
+        for _ in 0..self.len()? {
+            constants.push(self.constant()?);
+        }
+
+        let mut spans = vec![];
+        for _ in 0..self.len()? {
+            spans.push((self.len()?, self.span()?));
+        }
+
+        // Every operation must be covered by a span, in order.
+        if spans.first().is_none_or(|(start, _)| *start != 0)
+            || spans.iter().any(|(start, _)| *start >= code.len())
+            || spans.windows(2).any(|pair| pair[0].0 >= pair[1].0)
+        {
+            return None;
+        }
+
+        Some(Chunk::from_parts(code, constants, spans))
+    }
+
+    fn constant(&mut self) -> Option<Value> {
+        let value = match self.byte()? {
+            TAG_NULL => Value::Null,
+            TAG_BOOL => Value::Bool(self.bool()?),
+            TAG_INTEGER => {
+                let (i, len) = i64::decode_var(self.data)?;
+                self.data = &self.data[len..];
+                Value::Integer(i)
+            }
+            TAG_FLOAT => {
+                let (bytes, rest) = self.data.split_first_chunk::<8>()?;
+                self.data = rest;
+                Value::Float(f64::from_bits(u64::from_le_bytes(*bytes)))
+            }
+            TAG_STRING => Value::String(self.bytes()?.into()),
+            TAG_PATH => Value::Path(self.path()?),
+            TAG_UNRESOLVED_PATH => Value::UnresolvedPath(self.path()?),
+            TAG_FINALISE_REQUEST => Value::FinaliseRequest(self.bool()?),
+            TAG_EMPTY_ATTRS => Value::Attrs(Box::new(NixAttrs::empty())),
+            TAG_ATTR_NOT_FOUND => Value::AttrNotFound,
+            TAG_BLUEPRINT => Value::Blueprint(Rc::new(self.lambda()?)),
+            TAG_CLOSURE => {
+                let lambda = self.lambda()?;
+                if lambda.upvalue_count != 0 {
+                    return None;
+                }
+
+                Value::Closure(Rc::new(Closure::new(Rc::new(lambda))))
+            }
+            TAG_THUNK => {
+                let lambda = self.lambda()?;
+                if lambda.upvalue_count != 0 || lambda.formals.is_some() {
+                    return None;
+                }
+
+                Value::Thunk(Thunk::new_suspended(Rc::new(lambda), self.span()?))
+            }
+            TAG_GLOBAL => {
+                let name = self.bytes()?.to_str().ok()?;
+                self.globals.get(name)?.clone()
+            }
+            _ => return None,
+        };
+
+        Some(value)
+    }
+}
+
+/// Checks that the code of a chunk only consists of complete, known
+/// operations ending in a return, and that their constant, upvalue and
+/// jump operands are valid for the chunk and the lambda it belongs to.
+/// The VM relies on this, and panics on invalid bytecode.
+///
+/// This guards against entries written by a different encoder, but not
+/// against crafted ones: stack depths and the stack indices of local
+/// operands are not checked, and the VM does not check them either.
+/// This is why the cache directory must be trusted.
+fn validate_code(chunk: &Chunk, upvalue_count: usize, has_formals: bool) -> Option<()> {
+    let code = &chunk.code;
+    let constants = &chunk.constants;
+
+    let uvarint = |ip: &mut usize| -> Option<u64> {
+        let (n, len) = u64::decode_var(code.get(*ip..)?)?;
+        *ip += len;
+        Some(n)
+    };
+
+    let mut op_starts = vec![false; code.len()];
+    let mut jump_targets = vec![];
+    let mut last_op = None;
+    let mut ip = 0;
+
+    while ip < code.len() {
+        op_starts[ip] = true;
+        let op = Op::from(code[ip]);
+        ip += 1;
+
+        match op.arg_type() {
+            OpArg::None => match op {
+                Op::Invalid => return None,
+                Op::ValidateClosedFormals if !has_formals => return None,
+                _ => {}
+            },
+
+            OpArg::Fixed => {
+                let offset = u16::from_le_bytes(*code.get(ip..)?.first_chunk::<2>()?);
+                ip += 2;
+                jump_targets.push(ip + offset as usize);
+            }
+
+            OpArg::Uvarint => {
+                let arg = uvarint(&mut ip)?;
+                match op {
+                    Op::Constant if arg >= constants.len() as u64 => return None,
+                    Op::GetUpvalue if arg >= upvalue_count as u64 => return None,
+                    _ => {}
+                }
+            }
+
+            OpArg::Custom => match op {
+                Op::CoerceToString => {
+                    code.get(ip)?;
+                    ip += 1;
+                }
+
+                Op::Closure | Op::ThunkClosure | Op::ThunkSuspended => {
+                    let idx: usize = uvarint(&mut ip)?.try_into().ok()?;
+                    let Some(Value::Blueprint(blueprint)) = constants.get(idx) else {
+                        return None;
+                    };
+
+                    if uvarint(&mut ip)? >> 1 != blueprint.upvalue_count as u64 {
+                        return None;
+                    }
+
+                    for _ in 0..blueprint.upvalue_count {
+                        let position = Position(uvarint(&mut ip)?);
+                        let valid = position.runtime_stack_index().is_some()
+                            || position.runtime_deferred_local().is_some()
+                            || position
+                                .runtime_upvalue_index()
+                                .is_some_and(|idx| idx.0 < upvalue_count);
+
+                        if !valid {
+                            return None;
+                        }
+                    }
+                }
+
+                _ => return None,
+            },
+        }
+
+        last_op = Some(op);
+    }
+
+    let valid_jumps = jump_targets
+        .into_iter()
+        .all(|target| op_starts.get(target) == Some(&true));
+
+    (last_op == Some(Op::Return) && valid_jumps).then_some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Persists a top-level lambda with the given code and constants,
+    /// bypassing all checks done when storing compiled code, and loads
+    /// it back.
+    fn store_and_load(code: Vec<u8>, constants: Vec<Value>) -> Option<Rc<Lambda>> {
+        let dir = tempfile::tempdir().unwrap();
+        let globals = GlobalsMap::default();
+        let file = codemap::CodeMap::new().add_file("test.nix".into(), "null".into());
+        let key = PersistentKey::new("null", Path::new("/test.nix"), false, &globals);
+
+        let lambda = Lambda {
+            chunk: Chunk::from_parts(code, constants, vec![(0, file.span)]),
+            ..Default::default()
+        };
+
+        let mut encoder = Encoder {
+            globals: &globals,
+            file: &file,
+            data: MAGIC.to_vec(),
+        };
+        encoder.lambda(&lambda).unwrap();
+        let digest = Sha256::digest(&encoder.data[MAGIC.len()..]);
+        encoder.data.extend_from_slice(&digest);
+        std::fs::write(key.path(dir.path()), &encoder.data).unwrap();
+
+        load(dir.path(), &key, &globals, &file)
+    }
+
+    #[test]
+    fn load_valid_bytecode() {
+        #[rustfmt::skip]
+        let code = vec![
+            Op::Constant as u8, 0,
+            Op::JumpIfTrue as u8, 2, 0,
+            Op::Constant as u8, 0,
+            Op::Return as u8,
+        ];
+
+        assert!(store_and_load(code, vec![Value::Null]).is_some());
+    }
+
+    #[test]
+    fn load_invalid_bytecode() {
+        let cases: &[(&str, &[u8])] = &[
+            ("unknown op", &[Op::Invalid as u8, Op::Return as u8]),
+            ("missing return", &[Op::Constant as u8, 0]),
+            ("truncated operand", &[Op::Return as u8, Op::Jump as u8, 0]),
+            (
+                "constant out of bounds",
+                &[Op::Constant as u8, 1, Op::Return as u8],
+            ),
+            (
+                "upvalue out of bounds",
+                &[Op::GetUpvalue as u8, 0, Op::Return as u8],
+            ),
+            (
+                "non-blueprint closure",
+                &[Op::Closure as u8, 0, 0, Op::Return as u8],
+            ),
+            (
+                "formals without lambda",
+                &[Op::ValidateClosedFormals as u8, Op::Return as u8],
+            ),
+            (
+                "jump into operand",
+                &[
+                    Op::Jump as u8,
+                    1,
+                    0,
+                    Op::Constant as u8,
+                    0,
+                    Op::Return as u8,
+                ],
+            ),
+            ("jump past end", &[Op::Jump as u8, 1, 0, Op::Return as u8]),
+        ];
+
+        for (name, code) in cases {
+            assert!(
+                store_and_load(code.to_vec(), vec![Value::Null]).is_none(),
+                "{name}: invalid bytecode must not be loaded"
+            );
+        }
+    }
+}
@@ -248,6 +248,15 @@ impl TrackedBindings {
         true
     }
 
+    /// Returns the span of the statically known key of an existing binding
+    /// with the given name, if there is one.
+    fn static_key_span(&self, c: &Compiler, name: &str) -> Option<Span> {
+        self.bindings.iter().find_map(|b| match &b.key_slot {
+            KeySlot::Static { slot, name: key } if key == name => c.scope()[*slot].span,
+            _ => None,
+        })
+    }
+
     /// Add a completely new binding to the tracked bindings.
     fn track_new(&mut self, key_slot: KeySlot, value_slot: LocalIdx, binding: Binding) {
         self.bindings.push(TrackedBinding {
@@ -475,6 +484,23 @@ impl Compiler<'_, '_> {
                 continue;
             }
 
+            // Statically known keys that could not be merged must not be
+            // defined twice. In recursive scopes this is already caught when
+            // declaring the local, and dynamic keys are only checked at runtime.
+            if kind == BindingsKind::Attrs {
+                if let Some(name) = expr_static_attr_str(&key) {
+                    if let Some(previous) = bindings.static_key_span(self, &name) {
+                        self.emit_error(
+                            &key,
+                            ErrorKind::DuplicateStaticAttrsKey {
+                                key: name.to_string(),
+                                previous,
+                            },
+                        );
+                    }
+                }
+            }
+
             *count += 1;
 
             let key_span = self.span_for(&key);
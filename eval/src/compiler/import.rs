@@ -6,11 +6,15 @@
 //! instance, or observers).
 
 use super::GlobalsMap;
+use bstr::ByteSlice;
 use genawaiter::rc::Gen;
-use std::rc::Weak;
+use rustc_hash::FxHashMap;
+use smol_str::SmolStr;
+use std::path::PathBuf;
+use std::rc::{Rc, Weak};
 
 use crate::{
-    ErrorKind, SourceCode, Value,
+    ErrorKind, ImportResolver, SourceCode, Value,
     builtins::coerce_value_to_path,
     generators::pin_generator,
     observer::NoOpObserver,
@@ -22,6 +26,9 @@ async fn import_impl(
     co: GenCo,
     globals: Weak<GlobalsMap>,
     source: SourceCode,
+    resolver: Option<Rc<dyn ImportResolver>>,
+    import_interpolated_paths: bool,
+    deny_deprecated: bool,
     mut args: Vec<Value>,
 ) -> Result<Value, ErrorKind> {
     // TODO(sterni): canon_path()?
@@ -34,15 +41,102 @@ async fn import_impl(
         path.push("default.nix");
     }
 
+    generators::request_record_import(&co, path.clone()).await;
+
     if let Some(cached) = generators::request_import_cache_lookup(&co, path.clone()).await {
         return Ok(cached);
     }
 
-    let mut reader = generators::request_open_file(&co, path.clone()).await;
+    let res = import_file(
+        &co,
+        globals,
+        source,
+        resolver,
+        import_interpolated_paths,
+        deny_deprecated,
+        path.clone(),
+        None,
+    )
+    .await?;
+    generators::request_import_cache_put(&co, path, res.clone()).await;
+
+    Ok(res)
+}
+
+async fn scoped_import_impl(
+    co: GenCo,
+    globals: Weak<GlobalsMap>,
+    source: SourceCode,
+    resolver: Option<Rc<dyn ImportResolver>>,
+    import_interpolated_paths: bool,
+    deny_deprecated: bool,
+    mut args: Vec<Value>,
+) -> Result<Value, ErrorKind> {
+    let mut path = match coerce_value_to_path(&co, args.pop().unwrap()).await? {
+        Err(cek) => return Ok(Value::Catchable(Box::new(cek))),
+        Ok(path) => path,
+    };
+
+    let scope = generators::request_force(&co, args.pop().unwrap()).await;
+    if scope.is_catchable() {
+        return Ok(scope);
+    }
+
+    let env = scope
+        .to_attrs()?
+        .iter()
+        .map(|(name, value)| Ok((SmolStr::new(name.to_str()?), value.clone())))
+        .collect::<Result<FxHashMap<_, _>, ErrorKind>>()?;
+
+    if path.is_dir() {
+        path.push("default.nix");
+    }
+
+    generators::request_record_import(&co, path.clone()).await;
+
+    // The result depends on the injected scope, so it can not be
+    // shared through the import cache.
+    import_file(
+        &co,
+        globals,
+        source,
+        resolver,
+        import_interpolated_paths,
+        deny_deprecated,
+        path,
+        Some(&env),
+    )
+    .await
+}
+
+/// Reads, parses and compiles the file at the given path, returning a
+/// thunk of its value. Any bindings in `env` are brought into scope
+/// for the compilation of the file.
+///
+/// The file is read through the [`ImportResolver`], if it resolves the
+/// path, and through the VM's IO handle otherwise.
+#[allow(clippy::too_many_arguments)] // internal API, no point making an indirection type
+async fn import_file(
+    co: &GenCo,
+    globals: Weak<GlobalsMap>,
+    source: SourceCode,
+    resolver: Option<Rc<dyn ImportResolver>>,
+    import_interpolated_paths: bool,
+    deny_deprecated: bool,
+    path: PathBuf,
+    env: Option<&FxHashMap<SmolStr, Value>>,
+) -> Result<Value, ErrorKind> {
     // We read to a String instead of a Vec<u8> because rnix only supports
     // string source files.
-    let mut contents = String::new();
-    reader.read_to_string(&mut contents)?;
+    let contents = match resolver.and_then(|resolver| resolver.resolve(&path)) {
+        Some(contents) => contents?,
+        None => {
+            let mut reader = generators::request_open_file(co, path.clone()).await;
+            let mut contents = String::new();
+            reader.read_to_string(&mut contents)?;
+            contents
+        }
+    };
 
     let parsed = rnix::ast::Root::parse(&contents);
     let errors = parsed.errors();
@@ -56,7 +150,7 @@ async fn import_impl(
         });
     }
 
-    let result = crate::compiler::compile(
+    let mut result = crate::compiler::compile(
         &parsed.tree().expr().unwrap(),
         Some(path.clone()),
         // The VM must ensure that a strong reference to the globals outlives
@@ -65,16 +159,21 @@ async fn import_impl(
         globals
             .upgrade()
             .expect("globals dropped while still in use"),
-        None,
+        env,
         &source,
         &file,
         &mut NoOpObserver::default(),
+        import_interpolated_paths,
     )
     .map_err(|err| ErrorKind::ImportCompilerError {
         path: path.clone(),
         errors: vec![err],
     })?;
 
+    if deny_deprecated {
+        crate::deny_deprecated_syntax(&mut result.warnings, &mut result.errors, &source);
+    }
+
     if !result.errors.is_empty() {
         return Err(ErrorKind::ImportCompilerError {
             path,
@@ -83,19 +182,15 @@ async fn import_impl(
     }
 
     for warning in result.warnings {
-        generators::emit_warning(&co, warning).await;
+        generators::emit_warning(co, warning).await;
     }
 
     // Compilation succeeded, we can construct a thunk from whatever it spat
     // out and return that.
-    let res = Value::Thunk(Thunk::new_suspended(
+    Ok(Value::Thunk(Thunk::new_suspended(
         result.lambda,
-        generators::request_span(&co).await,
-    ));
-
-    generators::request_import_cache_put(&co, path, res.clone()).await;
-
-    Ok(res)
+        generators::request_span(co).await,
+    )))
 }
 
 /// Constructs the `import` builtin. This builtin is special in that
@@ -103,7 +198,13 @@ async fn import_impl(
 /// track source code locations while invoking a compiler.
 // TODO: need to be able to pass through a CompilationObserver, too.
 // TODO: can the `SourceCode` come from the compiler?
-pub(super) fn builtins_import(globals: &Weak<GlobalsMap>, source: SourceCode) -> Builtin {
+pub(super) fn builtins_import(
+    globals: &Weak<GlobalsMap>,
+    source: SourceCode,
+    resolver: Option<Rc<dyn ImportResolver>>,
+    import_interpolated_paths: bool,
+    deny_deprecated: bool,
+) -> Builtin {
     // This (very cheap, once-per-compiler-startup) clone exists
     // solely in order to keep the borrow checker happy.  It
     // resolves the tension between the requirements of
@@ -115,7 +216,51 @@ pub(super) fn builtins_import(globals: &Weak<GlobalsMap>, source: SourceCode) ->
         Some("Import the given file and return the Nix value it evaluates to"),
         1,
         move |args| {
-            Gen::new(|co| pin_generator(import_impl(co, globals.clone(), source.clone(), args)))
+            Gen::new(|co| {
+                pin_generator(import_impl(
+                    co,
+                    globals.clone(),
+                    source.clone(),
+                    resolver.clone(),
+                    import_interpolated_paths,
+                    deny_deprecated,
+                    args,
+                ))
+            })
+        },
+    )
+}
+
+/// Constructs the `scopedImport` builtin, which works like `import`
+/// but additionally brings the attributes of the given set into scope
+/// for the imported file.
+pub(super) fn builtins_scoped_import(
+    globals: &Weak<GlobalsMap>,
+    source: SourceCode,
+    resolver: Option<Rc<dyn ImportResolver>>,
+    import_interpolated_paths: bool,
+    deny_deprecated: bool,
+) -> Builtin {
+    let globals = globals.clone();
+
+    Builtin::new(
+        "scopedImport",
+        Some(
+            "Import the given file with the given attributes in scope and return the Nix value it evaluates to",
+        ),
+        2,
+        move |args| {
+            Gen::new(|co| {
+                pin_generator(scoped_import_impl(
+                    co,
+                    globals.clone(),
+                    source.clone(),
+                    resolver.clone(),
+                    import_interpolated_paths,
+                    deny_deprecated,
+                    args,
+                ))
+            })
         },
     )
 }
@@ -18,6 +18,8 @@ mod import;
 mod optimiser;
 mod scope;
 
+pub use optimiser::{Optimisation, optimise};
+
 use codemap::Span;
 use rnix::ast::{self, AstToken};
 use rustc_hash::FxHashMap;
@@ -30,8 +32,9 @@ use crate::CoercionKind;
 use crate::SourceCode;
 use crate::chunk::Chunk;
 use crate::errors::{CatchableErrorKind, Error, ErrorKind, EvalResult};
+use crate::io::ImportResolver;
 use crate::observer::CompilerObserver;
-use crate::opcode::{CodeIdx, Op, Position, UpvalueIdx};
+use crate::opcode::{CodeIdx, ConstantIdx, Op, Position, UpvalueIdx};
 use crate::spans::ToSpan;
 use crate::value::{Closure, Formals, Lambda, NixAttrs, Thunk, Value};
 use crate::warnings::{EvalWarning, WarningKind};
@@ -47,6 +50,35 @@ pub struct CompilationOutput {
     pub errors: Vec<Error>,
 }
 
+impl CompilationOutput {
+    /// Returns the value of the program if it is trivially constant
+    /// (e.g. a literal), in which case the VM does not need to run it.
+    pub fn is_constant(&self) -> Option<Value> {
+        if !self.errors.is_empty() {
+            return None;
+        }
+
+        let chunk = &self.lambda.chunk;
+        if Op::from(*chunk.code.first()?) != Op::Constant {
+            return None;
+        }
+
+        // The constant may be followed by the top-level `OpForce`,
+        // which is a no-op for anything but thunks.
+        let (idx, len) = chunk.read_uvarint(1);
+        match &chunk.code[1 + len..] {
+            [force, ret] if *force == Op::Force as u8 && *ret == Op::Return as u8 => {}
+            [ret] if *ret == Op::Return as u8 => {}
+            _ => return None,
+        }
+
+        match chunk.get_constant(ConstantIdx(idx as usize))? {
+            Value::Thunk(_) | Value::Catchable(_) => None,
+            value => Some(value.clone()),
+        }
+    }
+}
+
 /// Represents the lambda currently being compiled.
 struct LambdaCtx {
     lambda: Lambda,
@@ -119,8 +151,9 @@ pub type GlobalsMap = FxHashMap<&'static str, Value>;
 
 /// Set of builtins that (if they exist) should be made available in
 /// the global scope, meaning that they can be accessed not just
-/// through `builtins.<name>`, but directly as `<name>`. This is not
-/// configurable, it is based on what Nix 2.3 exposed.
+/// through `builtins.<name>`, but directly as `<name>`. It is based
+/// on what Nix 2.3 exposed; callers can only hide some of these, see
+/// [`prepare_globals`].
 const GLOBAL_BUILTINS: &[&str] = &[
     "abort",
     "baseNameOf",
@@ -172,6 +205,10 @@ pub struct Compiler<'source, 'observer> {
     /// compiler not to emit anything. This used for compiling dead
     /// code branches to catch errors & warnings in them.
     dead_scope: usize,
+
+    /// Whether paths interpolated into strings are imported into the
+    /// store, or coerced to their plain string form instead.
+    import_interpolated_paths: bool,
 }
 
 impl Compiler<'_, '_> {
@@ -189,6 +226,7 @@ impl<'source, 'observer> Compiler<'source, 'observer> {
         source: &'source SourceCode,
         file: &'source codemap::File,
         observer: &'observer mut dyn CompilerObserver,
+        import_interpolated_paths: bool,
     ) -> EvalResult<Self> {
         let mut root_dir = match location {
             Some(dir) if cfg!(target_arch = "wasm32") || dir.is_absolute() => Ok(dir),
@@ -230,6 +268,7 @@ impl<'source, 'observer> Compiler<'source, 'observer> {
             warnings: vec![],
             errors: vec![],
             dead_scope: 0,
+            import_interpolated_paths,
         };
 
         if let Some(env) = env {
@@ -477,7 +516,7 @@ impl Compiler<'_, '_> {
 
                     let encoded: u8 = CoercionKind {
                         strong: false,
-                        import_paths: true,
+                        import_paths: self.import_interpolated_paths,
                     }
                     .into();
 
@@ -1015,10 +1054,14 @@ impl Compiler<'_, '_> {
         let (set_idx, pat_bind_name) = match pattern.pat_bind() {
             Some(name) => {
                 let pat_bind_name = name.ident().unwrap().to_string();
-                (
-                    self.declare_local(&name, pat_bind_name.clone()),
-                    Some(pat_bind_name),
-                )
+                let idx = self.declare_local(&name, pat_bind_name.clone());
+
+                // Unused formals are warned about individually when the
+                // scope ends, but the `@`-bound name commonly exists only
+                // to document the argument and is never warned about.
+                self.scope_mut().mark_used(idx);
+
+                (idx, Some(pat_bind_name))
             }
             None => (self.scope_mut().declare_phantom(span, true), None),
         };
@@ -1497,7 +1540,7 @@ fn expr_static_str(node: &ast::Str) -> Option<SmolStr> {
 
 /// Convert the provided `ast::Attr` into a statically known string if
 /// possible.
-fn expr_static_attr_str(node: &ast::Attr) -> Option<SmolStr> {
+pub(crate) fn expr_static_attr_str(node: &ast::Attr) -> Option<SmolStr> {
     match node {
         ast::Attr::Ident(ident) => Some(ident.ident_token().unwrap().text().into()),
         ast::Attr::Str(s) => expr_static_str(s),
@@ -1550,6 +1593,7 @@ fn compile_src_builtin(
             &source,
             &file,
             &mut crate::observer::NoOpObserver {},
+            true,
         )
         .map_err(|e| ErrorKind::NativeError {
             gen_type: "derivation",
@@ -1572,14 +1616,24 @@ fn compile_src_builtin(
 /// which are made available globally under the `builtins` identifier.
 ///
 /// A subset of builtins (specified by [`GLOBAL_BUILTINS`]) is
-/// available globally *iff* they are set.
+/// available globally *iff* they are set and not listed in
+/// `hidden_globals`. Hidden builtins remain available in `builtins`.
 ///
-/// Optionally adds the `import` feature if desired by the caller.
+/// Optionally adds the `import` (and `scopedImport`) feature if
+/// desired by the caller, reading files through the given
+/// [`ImportResolver`] first, if any. Imported files are compiled with
+/// the given `import_interpolated_paths` setting, and fail to import if
+/// they use deprecated syntax while `deny_deprecated` is set.
+#[allow(clippy::too_many_arguments)]
 pub fn prepare_globals(
     builtins: Vec<(&'static str, Value)>,
     src_builtins: Vec<(&'static str, &'static str)>,
     source: SourceCode,
     enable_import: bool,
+    import_resolver: Option<Rc<dyn ImportResolver>>,
+    import_interpolated_paths: bool,
+    deny_deprecated: bool,
+    hidden_globals: &[&str],
 ) -> Rc<GlobalsMap> {
     Rc::new_cyclic(Box::new(move |weak: &Weak<GlobalsMap>| {
         // First step is to construct the builtins themselves as
@@ -1591,8 +1645,23 @@ pub fn prepare_globals(
         // to instantiate its compiler, the `Weak` reference is passed
         // here.
         if enable_import {
-            let import = Value::Builtin(import::builtins_import(weak, source.clone()));
+            let import = Value::Builtin(import::builtins_import(
+                weak,
+                source.clone(),
+                import_resolver.clone(),
+                import_interpolated_paths,
+                deny_deprecated,
+            ));
             builtins.insert("import", import);
+
+            let scoped_import = Value::Builtin(import::builtins_scoped_import(
+                weak,
+                source.clone(),
+                import_resolver,
+                import_interpolated_paths,
+                deny_deprecated,
+            ));
+            builtins.insert("scopedImport", scoped_import);
         }
 
         // Next, the actual map of globals which the compiler will use
@@ -1637,6 +1706,10 @@ pub fn prepare_globals(
         // Finally, the builtins that should be globally available are
         // "elevated" to the outer scope.
         for global in GLOBAL_BUILTINS {
+            if hidden_globals.contains(global) {
+                continue;
+            }
+
             if let Some(builtin) = builtins.get(global).cloned() {
                 globals.insert(global, builtin);
             }
@@ -1646,6 +1719,7 @@ pub fn prepare_globals(
     }))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn compile(
     expr: &ast::Expr,
     location: Option<PathBuf>,
@@ -1654,8 +1728,17 @@ pub fn compile(
     source: &SourceCode,
     file: &codemap::File,
     observer: &mut dyn CompilerObserver,
+    import_interpolated_paths: bool,
 ) -> EvalResult<CompilationOutput> {
-    let mut c = Compiler::new(location, globals.clone(), env, source, file, observer)?;
+    let mut c = Compiler::new(
+        location,
+        globals.clone(),
+        env,
+        source,
+        file,
+        observer,
+        import_interpolated_paths,
+    )?;
 
     let root_span = c.span_for(expr);
     let root_slot = c.scope_mut().declare_phantom(root_span, false);
@@ -1,16 +1,72 @@
 //! Helper functions for extending the compiler with more linter-like
 //! functionality while compiling (i.e. smarter warnings).
+//!
+//! The optimisations themselves are available independently of the
+//! compiler through [`optimise`], e.g. for tooling that wants to report
+//! optimisation opportunities.
+//!
+//! Only optimisations on the AST live here. Selecting statically known
+//! attributes from constant attribute sets (e.g. `builtins.length`) is
+//! optimised on the emitted bytecode instead, see
+//! `Compiler::optimise_select`, and is not exposed through [`optimise`].
 
 use super::*;
 
 use ast::Expr;
 
+/// An optimisation applicable to an expression, as found by [`optimise`].
+#[derive(Clone, Debug)]
+pub struct Optimisation {
+    /// Expression that the optimised expression can be replaced with.
+    pub replacement: Expr,
+
+    /// Expression that the optimisation is reported at.
+    pub culprit: Expr,
+
+    /// Human-readable description of the optimisation.
+    pub message: &'static str,
+
+    /// Sub-expression that is never evaluated after the optimisation,
+    /// if any.
+    pub dead_code: Option<Expr>,
+}
+
+/// Find an optimisation applicable to the given expression, without
+/// descending into its sub-expressions.
+///
+/// This assumes that the identifiers `true` and `false` refer to the
+/// boolean literals, i.e. that they are not shadowed in the scope of the
+/// expression.
+pub fn optimise(expr: &Expr) -> Option<Optimisation> {
+    match expr {
+        Expr::BinOp(op) => optimise_bin_op(op),
+        _ => None,
+    }
+}
+
 /// Optimise the given expression where possible.
 pub(super) fn optimise_expr(c: &mut Compiler, slot: LocalIdx, expr: ast::Expr) -> ast::Expr {
-    match expr {
-        Expr::BinOp(_) => optimise_bin_op(c, slot, expr),
-        _ => expr,
+    // bail out of this check if the user has overridden either `true`
+    // or `false` identifiers. Note that they will have received a
+    // separate warning about this for shadowing the global(s).
+    if c.is_user_defined("true") || c.is_user_defined("false") {
+        return expr;
     }
+
+    let Some(optimisation) = optimise(&expr) else {
+        return expr;
+    };
+
+    c.emit_warning(
+        &optimisation.culprit,
+        WarningKind::UselessBoolOperation(optimisation.message),
+    );
+
+    if let Some(dead_code) = optimisation.dead_code {
+        c.compile_dead_code(slot, dead_code);
+    }
+
+    optimisation.replacement
 }
 
 enum LitBool {
@@ -33,93 +89,69 @@ fn is_lit_bool(expr: ast::Expr) -> LitBool {
 }
 
 /// Detect useless binary operations (i.e. useless bool comparisons).
-fn optimise_bin_op(c: &mut Compiler, slot: LocalIdx, expr: ast::Expr) -> ast::Expr {
+fn optimise_bin_op(op: &ast::BinOp) -> Option<Optimisation> {
     use ast::BinOpKind;
 
-    // bail out of this check if the user has overridden either `true`
-    // or `false` identifiers. Note that they will have received a
-    // separate warning about this for shadowing the global(s).
-    if c.is_user_defined("true") || c.is_user_defined("false") {
-        return expr;
-    }
-
-    if let Expr::BinOp(op) = &expr {
-        let lhs = is_lit_bool(op.lhs().unwrap());
-        let rhs = is_lit_bool(op.rhs().unwrap());
-
-        match (op.operator().unwrap(), lhs, rhs) {
-            // useless `false` arm in `||` expression
-            (BinOpKind::Or, LitBool::False(f), LitBool::Expr(other))
-            | (BinOpKind::Or, LitBool::Expr(other), LitBool::False(f)) => {
-                c.emit_warning(
-                    &f,
-                    WarningKind::UselessBoolOperation(
-                        "this `false` has no effect on the result of the comparison",
-                    ),
-                );
-
-                return other;
-            }
-
-            // useless `true` arm in `&&` expression
-            (BinOpKind::And, LitBool::True(t), LitBool::Expr(other))
-            | (BinOpKind::And, LitBool::Expr(other), LitBool::True(t)) => {
-                c.emit_warning(
-                    &t,
-                    WarningKind::UselessBoolOperation(
-                        "this `true` has no effect on the result of the comparison",
-                    ),
-                );
-
-                return other;
-            }
-
-            // useless `||` expression (one arm is `true`), return
-            // `true` directly (and warn about dead code on the right)
-            (BinOpKind::Or, LitBool::True(t), LitBool::Expr(other)) => {
-                c.emit_warning(
-                    op,
-                    WarningKind::UselessBoolOperation("this expression is always true"),
-                );
-
-                c.compile_dead_code(slot, other);
-
-                return t;
+    let lhs = is_lit_bool(op.lhs().unwrap());
+    let rhs = is_lit_bool(op.rhs().unwrap());
+    let whole = || Expr::BinOp(op.clone());
+
+    let optimisation = match (op.operator().unwrap(), lhs, rhs) {
+        // useless `false` arm in `||` expression
+        (BinOpKind::Or, LitBool::False(f), LitBool::Expr(other))
+        | (BinOpKind::Or, LitBool::Expr(other), LitBool::False(f)) => Optimisation {
+            replacement: other,
+            culprit: f,
+            message: "this `false` has no effect on the result of the comparison",
+            dead_code: None,
+        },
+
+        // useless `true` arm in `&&` expression
+        (BinOpKind::And, LitBool::True(t), LitBool::Expr(other))
+        | (BinOpKind::And, LitBool::Expr(other), LitBool::True(t)) => Optimisation {
+            replacement: other,
+            culprit: t,
+            message: "this `true` has no effect on the result of the comparison",
+            dead_code: None,
+        },
+
+        // useless `||` expression (one arm is `true`), return
+        // `true` directly (and warn about dead code on the right)
+        (BinOpKind::Or, LitBool::True(t), LitBool::Expr(other)) => Optimisation {
+            replacement: t,
+            culprit: whole(),
+            message: "this expression is always true",
+            dead_code: Some(other),
+        },
+
+        (BinOpKind::Or, _, LitBool::True(t)) | (BinOpKind::Or, LitBool::True(t), _) => {
+            Optimisation {
+                replacement: t,
+                culprit: whole(),
+                message: "this expression is always true",
+                dead_code: None,
             }
+        }
 
-            (BinOpKind::Or, _, LitBool::True(t)) | (BinOpKind::Or, LitBool::True(t), _) => {
-                c.emit_warning(
-                    op,
-                    WarningKind::UselessBoolOperation("this expression is always true"),
-                );
-
-                return t;
-            }
-
-            // useless `&&` expression (one arm is `false), same as above
-            (BinOpKind::And, LitBool::False(f), LitBool::Expr(other)) => {
-                c.emit_warning(
-                    op,
-                    WarningKind::UselessBoolOperation("this expression is always false"),
-                );
-
-                c.compile_dead_code(slot, other);
-
-                return f;
-            }
-
-            (BinOpKind::And, _, LitBool::False(f)) | (BinOpKind::Or, LitBool::False(f), _) => {
-                c.emit_warning(
-                    op,
-                    WarningKind::UselessBoolOperation("this expression is always false"),
-                );
-
-                return f;
+        // useless `&&` expression (one arm is `false), same as above
+        (BinOpKind::And, LitBool::False(f), LitBool::Expr(other)) => Optimisation {
+            replacement: f,
+            culprit: whole(),
+            message: "this expression is always false",
+            dead_code: Some(other),
+        },
+
+        (BinOpKind::And, _, LitBool::False(f)) | (BinOpKind::Or, LitBool::False(f), _) => {
+            Optimisation {
+                replacement: f,
+                culprit: whole(),
+                message: "this expression is always false",
+                dead_code: None,
             }
-
-            _ => { /* nothing to optimise */ }
         }
-    }
 
-    expr
+        _ => return None, // nothing to optimise
+    };
+
+    Some(optimisation)
 }
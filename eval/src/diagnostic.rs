@@ -0,0 +1,62 @@
+//! Machine-readable representation of evaluation errors and warnings,
+//! e.g. for consumption by editors or CI systems.
+//!
+//! Unlike the fancy output of [`Error::fancy_format_stderr`], these
+//! only describe the primary span of an error or warning.
+//!
+//! [`Error::fancy_format_stderr`]: crate::Error::fancy_format_stderr
+
+use codemap::Span;
+use serde::Serialize;
+
+use crate::SourceCode;
+
+/// How severe a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Structured data about an evaluation error or warning.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// Name of the file the diagnostic refers to, as it would be
+    /// displayed in human-readable output.
+    pub file: String,
+
+    /// Byte offset of the start of the diagnostic's span in `file`.
+    pub start: usize,
+
+    /// Byte offset of the end of the diagnostic's span in `file`
+    /// (exclusive).
+    pub end: usize,
+
+    pub severity: Severity,
+
+    pub message: String,
+
+    /// Unique code of the kind of error or warning, e.g. `E006`.
+    pub code: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(
+        source: &SourceCode,
+        span: Span,
+        severity: Severity,
+        message: String,
+        code: &str,
+    ) -> Self {
+        let file = source.get_file(span);
+        Diagnostic {
+            file: file.name().to_string(),
+            start: (span.low() - file.span.low()) as usize,
+            end: (span.high() - file.span.low()) as usize,
+            severity,
+            message,
+            code: code.to_string(),
+        }
+    }
+}
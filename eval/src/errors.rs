@@ -11,6 +11,7 @@ use codemap::{File, Span};
 use codemap_diagnostic::{ColorConfig, Diagnostic, Emitter, Level, SpanLabel, SpanStyle};
 use smol_str::SmolStr;
 
+use crate::diagnostic::Severity;
 use crate::spans::ToSpan;
 use crate::value::{CoercionKind, NixString};
 use crate::{SourceCode, Value};
@@ -60,9 +61,22 @@ pub enum ErrorKind {
     #[error("division by zero")]
     DivisionByZero,
 
+    /// Integer arithmetic overflowed the 64-bit range of Nix integers.
+    #[error("integer overflow in {lhs} {op} {rhs}")]
+    IntegerOverflow {
+        op: &'static str,
+        lhs: i64,
+        rhs: i64,
+    },
+
     #[error("attribute key '{key}' already defined")]
     DuplicateAttrsKey { key: String },
 
+    /// Statically known attribute key defined more than once in the
+    /// same attribute set, detected at compile time.
+    #[error("attribute key '{key}' already defined")]
+    DuplicateStaticAttrsKey { key: String, previous: Span },
+
     /// Attempted to specify an invalid key type (e.g. integer) in a
     /// dynamic attribute name.
     #[error(
@@ -269,6 +283,13 @@ to a missing value in the attribute set(s) included via `with`."#
     #[error("feature not yet implemented in Tvix: {0}")]
     NotImplemented(&'static str),
 
+    /// The configured [`EvalIO`](crate::EvalIO) can not support an
+    /// operation, e.g. because it only simulates a store. Unlike
+    /// [`ErrorKind::NotImplemented`], this is not a missing feature of
+    /// Tvix itself.
+    #[error("unsupported by the configured store: {0}")]
+    UnsupportedByIO(&'static str),
+
     /// Internal variant which should disappear during error construction.
     #[error("internal ErrorKind::WithContext variant leaked")]
     WithContext {
@@ -293,6 +314,20 @@ to a missing value in the attribute set(s) included via `with`."#
     /// or "sha512"
     #[error("unknown hash type '{0}'")]
     UnknownHashType(String),
+
+    /// Evaluation executed more operations than permitted by the
+    /// configured budget.
+    #[error("evaluation exceeded its budget of {budget} operations")]
+    OpBudgetExceeded { budget: u64 },
+
+    /// Evaluation nested calls deeper than permitted by the configured
+    /// maximum call depth.
+    #[error("evaluation exceeded the maximum call depth of {max_call_depth}")]
+    StackOverflow { max_call_depth: usize },
+
+    /// Deprecated syntax was used while deprecations are denied.
+    #[error("{0} are deprecated")]
+    DeprecatedSyntax(&'static str),
 }
 
 impl error::Error for Error {
@@ -631,16 +666,30 @@ impl Error {
         Emitter::stderr(ColorConfig::Auto, Some(&*self.source.codemap())).emit(&self.diagnostics());
     }
 
+    /// Create a machine-readable description of this error, pointing
+    /// at its primary span.
+    pub fn to_diagnostic(&self) -> crate::diagnostic::Diagnostic {
+        crate::diagnostic::Diagnostic::new(
+            &self.source,
+            self.span,
+            Severity::Error,
+            self.to_string(),
+            self.code(),
+        )
+    }
+
     /// Create the optional span label displayed as an annotation on
     /// the underlined span of the error.
     fn span_label(&self) -> Option<String> {
         let label = match &self.kind {
             ErrorKind::DuplicateAttrsKey { .. } => "in this attribute set",
+            ErrorKind::DuplicateStaticAttrsKey { .. } => "defined again here",
             ErrorKind::InvalidAttributeName(_) => "in this attribute set",
             ErrorKind::RelativePathResolution(_) => "in this path literal",
             ErrorKind::UnexpectedArgumentBuiltin { .. } => "while calling this builtin",
             ErrorKind::UnexpectedArgumentFormals { .. } => "in this function call",
             ErrorKind::UnexpectedContext => "in this string",
+            ErrorKind::DeprecatedSyntax(_) => "deprecated syntax used here",
 
             // The spans for some errors don't have any more descriptive stuff
             // in them, or we don't utilise it yet.
@@ -651,6 +700,7 @@ impl Error {
             | ErrorKind::TypeError { .. }
             | ErrorKind::Incomparable { .. }
             | ErrorKind::DivisionByZero
+            | ErrorKind::IntegerOverflow { .. }
             | ErrorKind::DynamicKeyInScope(_)
             | ErrorKind::UnknownStaticVariable
             | ErrorKind::UnknownDynamicVariable(_)
@@ -675,9 +725,12 @@ impl Error {
             | ErrorKind::TvixError(_)
             | ErrorKind::TvixBug { .. }
             | ErrorKind::NotImplemented(_)
+            | ErrorKind::UnsupportedByIO(_)
             | ErrorKind::WithContext { .. }
             | ErrorKind::UnknownHashType(_)
             | ErrorKind::InvalidHash(_)
+            | ErrorKind::OpBudgetExceeded { .. }
+            | ErrorKind::StackOverflow { .. }
             | ErrorKind::CatchableError(_) => return None,
         };
 
@@ -725,6 +778,12 @@ impl Error {
             ErrorKind::UnknownHashType(_) => "E039",
             ErrorKind::UnexpectedArgumentBuiltin { .. } => "E040",
             ErrorKind::InvalidHash(_) => "E041",
+            ErrorKind::OpBudgetExceeded { .. } => "E042",
+            ErrorKind::DeprecatedSyntax(_) => "E043",
+            ErrorKind::DuplicateStaticAttrsKey { .. } => "E044",
+            ErrorKind::StackOverflow { .. } => "E045",
+            ErrorKind::IntegerOverflow { .. } => "E046",
+            ErrorKind::UnsupportedByIO(_) => "E047",
 
             // Special error code for errors from other Tvix
             // components. We may want to introduce a code namespacing
@@ -777,6 +836,21 @@ impl Error {
                 ]
             }
 
+            ErrorKind::DuplicateStaticAttrsKey { previous, .. } => {
+                vec![
+                    SpanLabel {
+                        label: self.span_label(),
+                        span: self.span,
+                        style: SpanStyle::Primary,
+                    },
+                    SpanLabel {
+                        label: Some("first defined here".into()),
+                        span: *previous,
+                        style: SpanStyle::Secondary,
+                    },
+                ]
+            }
+
             ErrorKind::InfiniteRecursion {
                 first_force,
                 suspended_at,
@@ -116,6 +116,16 @@ pub trait EvalIO {
     }
 }
 
+/// Overrides how files are read by `import` (and `scopedImport`), e.g. to
+/// serve Nix files from somewhere other than the filesystem.
+///
+/// The resolver is consulted before reading a file through [`EvalIO`].
+pub trait ImportResolver {
+    /// Returns the source code of the file at the given path, or [`None`]
+    /// if the file should be read through [`EvalIO`] as usual.
+    fn resolve(&self, path: &Path) -> Option<io::Result<String>>;
+}
+
 /// Implementation of [`EvalIO`] that simply uses the equivalent
 /// standard library functions, i.e. does local file-IO.
 #[cfg(feature = "impure")]
@@ -14,7 +14,9 @@
 
 pub mod builtins;
 mod chunk;
+mod compilation_cache;
 mod compiler;
+mod diagnostic;
 mod errors;
 mod io;
 pub mod observer;
@@ -37,19 +39,25 @@ mod test_utils;
 mod tests;
 
 use rustc_hash::FxHashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::str::FromStr;
 use std::sync::Arc;
 
+use crate::compilation_cache::persistent::{self, PersistentKey};
+use crate::compilation_cache::{CacheKey, CachedCompilation};
 use crate::observer::{CompilerObserver, RuntimeObserver};
 use crate::value::Lambda;
-use crate::vm::run_lambda;
+use crate::vm::{RuntimeResult, run_deep_force, run_lambda};
 
 // Re-export the public interface used by other crates.
-pub use crate::compiler::{CompilationOutput, GlobalsMap, compile, prepare_globals};
+pub use crate::compilation_cache::CompilationCache;
+pub use crate::compiler::{
+    CompilationOutput, GlobalsMap, Optimisation, compile, optimise, prepare_globals,
+};
+pub use crate::diagnostic::{Diagnostic, Severity};
 pub use crate::errors::{AddContext, CatchableErrorKind, Error, ErrorKind, EvalResult};
-pub use crate::io::{DummyIO, EvalIO, FileType};
+pub use crate::io::{DummyIO, EvalIO, FileType, ImportResolver};
 pub use crate::nix_search_path::NixSearchPath;
 pub use crate::pretty_ast::pretty_print_expr;
 pub use crate::source::SourceCode;
@@ -59,7 +67,9 @@ pub use crate::warnings::{EvalWarning, WarningKind};
 pub use builtin_macros;
 use smol_str::SmolStr;
 
-pub use crate::value::{Builtin, CoercionKind, NixAttrs, NixList, NixString, Value};
+pub use crate::value::{
+    Builtin, CoercionKind, DetailedType, NixAttrs, NixList, NixString, PointerEquality, Value,
+};
 
 #[cfg(feature = "impure")]
 pub use crate::io::StdIO;
@@ -67,6 +77,7 @@ pub use crate::io::StdIO;
 struct BuilderBuiltins {
     builtins: Vec<(&'static str, Value)>,
     src_builtins: Vec<(&'static str, &'static str)>,
+    hidden_globals: Vec<&'static str>,
 }
 
 enum BuilderGlobals {
@@ -94,6 +105,14 @@ pub struct EvaluationBuilder<'co, 'ro, 'env> {
     nix_path: Option<String>,
     compiler_observer: Option<&'co mut dyn CompilerObserver>,
     runtime_observer: Option<&'ro mut dyn RuntimeObserver>,
+    compilation_cache: Option<Rc<CompilationCache>>,
+    eval_cache_dir: Option<PathBuf>,
+    op_budget: Option<u64>,
+    max_call_depth: Option<usize>,
+    deny_deprecated: bool,
+    root_dir: Option<PathBuf>,
+    import_resolver: Option<Rc<dyn ImportResolver>>,
+    import_interpolated_paths: bool,
 }
 
 impl<'co, 'ro, 'env> EvaluationBuilder<'co, 'ro, 'env> {
@@ -112,6 +131,7 @@ impl<'co, 'ro, 'env> EvaluationBuilder<'co, 'ro, 'env> {
             BuilderGlobals::Builtins(BuilderBuiltins {
                 mut builtins,
                 src_builtins,
+                hidden_globals,
             }) => {
                 // Insert a storeDir builtin *iff* a store directory is present.
                 if let Some(store_dir) = self.io_handle.as_ref().store_dir() {
@@ -123,6 +143,10 @@ impl<'co, 'ro, 'env> EvaluationBuilder<'co, 'ro, 'env> {
                     src_builtins,
                     source_map.clone(),
                     self.enable_import,
+                    self.import_resolver,
+                    self.import_interpolated_paths,
+                    self.deny_deprecated,
+                    &hidden_globals,
                 )
             }
         };
@@ -136,6 +160,13 @@ impl<'co, 'ro, 'env> EvaluationBuilder<'co, 'ro, 'env> {
             nix_path: self.nix_path,
             compiler_observer: self.compiler_observer,
             runtime_observer: self.runtime_observer,
+            compilation_cache: self.compilation_cache,
+            eval_cache_dir: self.eval_cache_dir,
+            op_budget: self.op_budget,
+            max_call_depth: self.max_call_depth,
+            deny_deprecated: self.deny_deprecated,
+            root_dir: self.root_dir,
+            import_interpolated_paths: self.import_interpolated_paths,
         }
     }
 }
@@ -154,12 +185,21 @@ impl<'co, 'ro, 'env> EvaluationBuilder<'co, 'ro, 'env> {
             globals: BuilderGlobals::Builtins(BuilderBuiltins {
                 builtins,
                 src_builtins: vec![],
+                hidden_globals: vec![],
             }),
             env: None,
             mode: Default::default(),
             nix_path: None,
             compiler_observer: None,
             runtime_observer: None,
+            compilation_cache: None,
+            eval_cache_dir: None,
+            op_budget: None,
+            max_call_depth: None,
+            deny_deprecated: false,
+            root_dir: None,
+            import_resolver: None,
+            import_interpolated_paths: true,
         }
     }
 
@@ -174,6 +214,14 @@ impl<'co, 'ro, 'env> EvaluationBuilder<'co, 'ro, 'env> {
             nix_path: self.nix_path,
             compiler_observer: self.compiler_observer,
             runtime_observer: self.runtime_observer,
+            compilation_cache: self.compilation_cache,
+            eval_cache_dir: self.eval_cache_dir,
+            op_budget: self.op_budget,
+            max_call_depth: self.max_call_depth,
+            deny_deprecated: self.deny_deprecated,
+            root_dir: self.root_dir,
+            import_resolver: self.import_resolver,
+            import_interpolated_paths: self.import_interpolated_paths,
         }
     }
 
@@ -226,6 +274,20 @@ impl<'co, 'ro, 'env> EvaluationBuilder<'co, 'ro, 'env> {
         self
     }
 
+    /// Do not make the given builtins available in the global scope, even if they usually are
+    /// (e.g. `import`). They remain accessible through the `builtins` attribute set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this evaluation builder has had globals set via [`with_globals`]
+    pub fn hide_global_builtins<I>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = &'static str>,
+    {
+        self.builtins_mut().hidden_globals.extend(names);
+        self
+    }
+
     /// Set the globals for this evaluation builder to a previously-constructed globals map.
     /// Intended to allow sharing globals across multiple evaluations (eg for the REPL).
     ///
@@ -254,6 +316,71 @@ impl<'co, 'ro, 'env> EvaluationBuilder<'co, 'ro, 'env> {
         Self { mode, ..self }
     }
 
+    /// Abort evaluation with [`ErrorKind::OpBudgetExceeded`] after executing the given number of
+    /// VM operations. Evaluations are unlimited by default.
+    pub fn op_budget(self, op_budget: u64) -> Self {
+        Self {
+            op_budget: Some(op_budget),
+            ..self
+        }
+    }
+
+    /// Abort evaluation with [`ErrorKind::StackOverflow`] when calls (of functions, builtins or
+    /// thunks) are nested deeper than the given depth. Evaluations are unlimited by default.
+    pub fn max_call_depth(self, max_call_depth: usize) -> Self {
+        Self {
+            max_call_depth: Some(max_call_depth),
+            ..self
+        }
+    }
+
+    /// Turn warnings about deprecated syntax (URL literals, legacy
+    /// `let`) in the evaluated code and imported files into errors.
+    ///
+    /// For imported files, the setting becomes part of the globals, so
+    /// it has no effect on them if globals are set via
+    /// [`with_globals`](Self::with_globals).
+    pub fn deny_deprecated(self, deny_deprecated: bool) -> Self {
+        Self {
+            deny_deprecated,
+            ..self
+        }
+    }
+
+    /// Whether paths interpolated into strings (e.g. `"${./foo}"`) in the evaluated code are
+    /// imported into the store, which is the default. Otherwise, they are coerced to their plain
+    /// string form. This applies to imported files too.
+    ///
+    /// For imported files, the setting becomes part of the globals, so it has no effect on them if
+    /// globals are set via [`with_globals`](Self::with_globals).
+    pub fn import_interpolated_paths(self, import_interpolated_paths: bool) -> Self {
+        Self {
+            import_interpolated_paths,
+            ..self
+        }
+    }
+
+    /// Resolve relative path literals in code evaluated without a location against the given
+    /// (absolute) directory, instead of the current working directory.
+    pub fn root_dir(self, root_dir: PathBuf) -> Self {
+        Self {
+            root_dir: Some(root_dir),
+            ..self
+        }
+    }
+
+    /// Consult the given resolver for the contents of files read by `import`, before falling back
+    /// to reading them through the IO handle.
+    ///
+    /// The resolver becomes part of the globals, so it has no effect if globals are set via
+    /// [`with_globals`](Self::with_globals).
+    pub fn import_resolver(self, import_resolver: Box<dyn ImportResolver>) -> Self {
+        Self {
+            import_resolver: Some(import_resolver.into()),
+            ..self
+        }
+    }
+
     pub fn nix_path(self, nix_path: Option<String>) -> Self {
         Self { nix_path, ..self }
     }
@@ -289,6 +416,33 @@ impl<'co, 'ro, 'env> EvaluationBuilder<'co, 'ro, 'env> {
     pub fn set_runtime_observer(&mut self, runtime_observer: Option<&'ro mut dyn RuntimeObserver>) {
         self.runtime_observer = runtime_observer;
     }
+
+    /// Reuse bytecode compiled by previous evaluations of the same source code.
+    ///
+    /// Cached results only apply to evaluations that share their globals and source map, see
+    /// [`CompilationCache`] for details. Evaluations with a top-level [`env`](Self::env) are
+    /// never cached.
+    pub fn with_compilation_cache(self, compilation_cache: Rc<CompilationCache>) -> Self {
+        Self {
+            compilation_cache: Some(compilation_cache),
+            ..self
+        }
+    }
+
+    /// Persist bytecode compiled for the evaluated code in the given directory, and reuse it in
+    /// later evaluations (e.g. by other processes) of the same source code at the same location.
+    ///
+    /// Code compiled with warnings is never persisted, neither are evaluations with a top-level
+    /// [`env`](Self::env).
+    ///
+    /// Persisted bytecode is only partially validated when it is loaded, and crafted entries can
+    /// make the evaluator panic. The directory must not be writable by untrusted users.
+    pub fn eval_cache_dir(self, eval_cache_dir: Option<PathBuf>) -> Self {
+        Self {
+            eval_cache_dir,
+            ..self
+        }
+    }
 }
 
 impl EvaluationBuilder<'_, '_, '_> {
@@ -370,6 +524,30 @@ pub struct Evaluation<'co, 'ro, 'env> {
     /// (optional) runtime observer, for reporting on execution steps
     /// of Nix code.
     runtime_observer: Option<&'ro mut dyn RuntimeObserver>,
+
+    /// (optional) cache of previously compiled top-level expressions.
+    compilation_cache: Option<Rc<CompilationCache>>,
+
+    /// (optional) directory in which compiled top-level expressions
+    /// are persisted across evaluations.
+    eval_cache_dir: Option<PathBuf>,
+
+    /// (optional) maximum number of operations the VM may execute.
+    op_budget: Option<u64>,
+
+    /// (optional) maximum nesting depth of calls in the VM.
+    max_call_depth: Option<usize>,
+
+    /// Whether deprecated syntax is an error instead of a warning.
+    deny_deprecated: bool,
+
+    /// (optional) directory to resolve relative paths against if no
+    /// location is given, instead of the current working directory.
+    root_dir: Option<PathBuf>,
+
+    /// Whether paths interpolated into strings are imported into the
+    /// store.
+    import_interpolated_paths: bool,
 }
 
 /// Result of evaluating a piece of Nix code. If evaluation succeeded, a value
@@ -389,6 +567,10 @@ pub struct EvaluationResult {
 
     /// AST node that was parsed from the code (on success only).
     pub expr: Option<rnix::ast::Expr>,
+
+    /// Span of the evaluated code in the source map. Warnings and errors within it originate from
+    /// the code itself, all others e.g. from imported files.
+    pub entry_span: Option<codemap::Span>,
 }
 
 impl<'co, 'ro, 'env> Evaluation<'co, 'ro, 'env> {
@@ -406,6 +588,18 @@ impl<'co, 'ro, 'env> Evaluation<'co, 'ro, 'env> {
         self.globals.clone()
     }
 
+    /// Returns the sorted names of all builtins available through `builtins` in this evaluation,
+    /// e.g. to document what a given configuration of builtins exposes.
+    pub fn builtin_names(&self) -> Vec<String> {
+        match self.globals.get("builtins") {
+            Some(Value::Attrs(attrs)) => attrs
+                .keys_sorted()
+                .map(|name| String::from_utf8_lossy(name.as_bytes()).into_owned())
+                .collect(),
+            _ => vec![],
+        }
+    }
+
     /// Clone the reference to the contained source code map. This is used after an evaluation for
     /// pretty error printing. Also, if [`Value`]s are shared across subsequent [`Evaluation`]s, it
     /// is important that those evaluations all have the same underlying source code map.
@@ -437,26 +631,16 @@ impl Evaluation<'_, '_, '_> {
         location: Option<PathBuf>,
     ) -> EvaluationResult {
         let mut result = EvaluationResult::default();
-        let source = self.source_map();
-
-        let location_str = location
-            .as_ref()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|| "[code]".into());
-
-        let file = source.add_file(location_str, code.as_ref().to_string());
+        let cache_key = self.cache_key(code.as_ref(), location.as_deref());
 
         let mut noop_observer = observer::NoOpObserver::default();
         let compiler_observer = self.compiler_observer.take().unwrap_or(&mut noop_observer);
 
-        parse_compile_internal(
+        self.compile_toplevel(
             &mut result,
             code.as_ref(),
-            file,
             location,
-            source,
-            self.globals,
-            self.env,
+            cache_key.as_ref(),
             compiler_observer,
         );
 
@@ -473,25 +657,16 @@ impl Evaluation<'_, '_, '_> {
     ) -> EvaluationResult {
         let mut result = EvaluationResult::default();
         let source = self.source_map();
-
-        let location_str = location
-            .as_ref()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|| "[code]".into());
-
-        let file = source.add_file(location_str, code.as_ref().to_string());
+        let cache_key = self.cache_key(code.as_ref(), location.as_deref());
 
         let mut noop_observer = observer::NoOpObserver::default();
         let compiler_observer = self.compiler_observer.take().unwrap_or(&mut noop_observer);
 
-        let lambda = match parse_compile_internal(
+        let (file, lambda) = match self.compile_toplevel(
             &mut result,
             code.as_ref(),
-            file.clone(),
             location,
-            source.clone(),
-            self.globals.clone(),
-            self.env,
+            cache_key.as_ref(),
             compiler_observer,
         ) {
             None => return result,
@@ -501,21 +676,7 @@ impl Evaluation<'_, '_, '_> {
         // If bytecode was returned, there were no errors and the
         // code is safe to execute.
 
-        let nix_path = self
-            .nix_path
-            .as_ref()
-            .and_then(|s| match nix_search_path::NixSearchPath::from_str(s) {
-                Ok(path) => Some(path),
-                Err(err) => {
-                    result.warnings.push(EvalWarning {
-                        kind: WarningKind::InvalidNixPath(err.to_string()),
-                        span: file.span,
-                    });
-                    None
-                }
-            })
-            .unwrap_or_default();
-
+        let nix_path = self.nix_search_path(&mut result, file.span);
         let runtime_observer = self.runtime_observer.take().unwrap_or(&mut noop_observer);
 
         let vm_result = run_lambda(
@@ -526,28 +687,269 @@ impl Evaluation<'_, '_, '_> {
             self.globals,
             lambda,
             self.mode,
+            self.op_budget,
+            self.max_call_depth,
+        );
+
+        Self::add_runtime_result(&mut result, vm_result, file.span, source);
+
+        result
+    }
+
+    /// Deeply force the given value, e.g. one returned by a previous lazy evaluation sharing the
+    /// globals and source map of this one, evaluating all nested thunks. Errors anywhere in the
+    /// value are reported in the result, like for evaluations in [`EvalMode::Strict`].
+    pub fn deep_force(mut self, value: Value) -> EvaluationResult {
+        let mut result = EvaluationResult::default();
+        let source = self.source_map();
+
+        // Errors without a more specific location are reported at this
+        // (empty) file.
+        let file = source.add_file("[deep_force]".into(), String::new());
+        let nix_path = self.nix_search_path(&mut result, file.span);
+
+        let mut noop_observer = observer::NoOpObserver::default();
+        let runtime_observer = self.runtime_observer.take().unwrap_or(&mut noop_observer);
+
+        let vm_result = run_deep_force(
+            nix_path,
+            self.io_handle,
+            runtime_observer,
+            source.clone(),
+            self.globals,
+            value,
+            file.span,
+            self.op_budget,
+            self.max_call_depth,
         );
 
+        Self::add_runtime_result(&mut result, vm_result, file.span, source);
+        result
+    }
+
+    /// Parse the configured Nix search path, warning about (and
+    /// ignoring) it if it is invalid.
+    fn nix_search_path(&self, result: &mut EvaluationResult, span: codemap::Span) -> NixSearchPath {
+        self.nix_path
+            .as_ref()
+            .and_then(|s| match NixSearchPath::from_str(s) {
+                Ok(path) => Some(path),
+                Err(err) => {
+                    result.warnings.push(EvalWarning {
+                        kind: WarningKind::InvalidNixPath(err.to_string()),
+                        span,
+                    });
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    /// Record the value (or errors) and warnings of a VM run in the
+    /// given result.
+    fn add_runtime_result(
+        result: &mut EvaluationResult,
+        vm_result: EvalResult<RuntimeResult>,
+        span: codemap::Span,
+        source: SourceCode,
+    ) {
         match vm_result {
             Ok(mut runtime_result) => {
                 result.warnings.append(&mut runtime_result.warnings);
                 if let Value::Catchable(inner) = runtime_result.value {
-                    result.errors.push(Error::new(
-                        ErrorKind::CatchableError(*inner),
-                        file.span,
-                        source,
-                    ));
-                    return result;
+                    result
+                        .errors
+                        .push(Error::new(ErrorKind::CatchableError(*inner), span, source));
+                } else {
+                    result.value = Some(runtime_result.value);
                 }
-
-                result.value = Some(runtime_result.value);
             }
             Err(err) => {
                 result.errors.push(err);
             }
         }
+    }
 
-        result
+    /// Key under which the given code is stored in the compilation
+    /// cache, if caching applies to this evaluation.
+    ///
+    /// Bytecode compiled with a top-level environment depends on the
+    /// values in it, so such evaluations are never cached.
+    fn cache_key(&self, code: &str, location: Option<&Path>) -> Option<CacheKey> {
+        if self.env.is_some() {
+            return None;
+        }
+
+        self.compilation_cache.as_ref().map(|_| {
+            CacheKey::new(
+                code,
+                location.or(self.root_dir.as_deref()),
+                self.import_interpolated_paths,
+            )
+        })
+    }
+
+    /// Directory and key under which the given code is persisted, if
+    /// an eval cache directory is configured for this evaluation.
+    ///
+    /// Like for the in-memory cache, evaluations with a top-level
+    /// environment are never cached.
+    fn persistent_key(
+        &self,
+        code: &str,
+        location: Option<&Path>,
+    ) -> Option<(&Path, PersistentKey)> {
+        let dir = self.eval_cache_dir.as_deref()?;
+        if self.env.is_some() {
+            return None;
+        }
+
+        // Relative paths in the code are resolved against the location,
+        // or the current directory if there is none.
+        let location = match location {
+            Some(location) if location.is_absolute() => location.to_path_buf(),
+            location => std::env::current_dir()
+                .ok()?
+                .join(location.unwrap_or(Path::new(""))),
+        };
+
+        let key = PersistentKey::new(
+            code,
+            &location,
+            self.import_interpolated_paths,
+            &self.globals,
+        );
+
+        Some((dir, key))
+    }
+
+    /// Parse and compile the provided source code, or reuse the
+    /// result of a previous compilation from the compilation cache.
+    fn compile_toplevel(
+        &self,
+        result: &mut EvaluationResult,
+        code: &str,
+        location: Option<PathBuf>,
+        cache_key: Option<&CacheKey>,
+        compiler_observer: &mut dyn CompilerObserver,
+    ) -> Option<(Arc<codemap::File>, Rc<Lambda>)> {
+        let source = self.source_map();
+
+        let cached = match (&self.compilation_cache, cache_key) {
+            (Some(cache), Some(key)) => cache.get(key, &self.globals, &source),
+            _ => None,
+        };
+
+        let (file, lambda) = match cached {
+            Some(cached) => {
+                result.entry_span = Some(cached.file.span);
+                result.warnings = cached.warnings;
+                result.expr = cached.expr;
+                (cached.file, cached.lambda)
+            }
+
+            None => {
+                let location_str = location
+                    .as_ref()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "[code]".into());
+
+                let file = source.add_file(location_str, code.to_string());
+                result.entry_span = Some(file.span);
+                let location = location.or_else(|| self.root_dir.clone());
+                let persistent_key = self.persistent_key(code, location.as_deref());
+
+                let persisted = persistent_key
+                    .as_ref()
+                    .and_then(|(dir, key)| persistent::load(dir, key, &self.globals, &file));
+
+                let lambda = match persisted {
+                    Some(lambda) => {
+                        // Parsing is cheap compared to compilation, and
+                        // the expression is not persisted.
+                        result.expr = rnix::ast::Root::parse(code).tree().expr();
+                        lambda
+                    }
+
+                    None => {
+                        let lambda = parse_compile_internal(
+                            result,
+                            code,
+                            file.clone(),
+                            location,
+                            source.clone(),
+                            self.globals.clone(),
+                            self.env,
+                            compiler_observer,
+                            self.import_interpolated_paths,
+                        )?;
+
+                        if let (Some((dir, key)), Some(expr)) = (&persistent_key, &result.expr) {
+                            if result.warnings.is_empty() {
+                                // Failing to persist bytecode only makes
+                                // later evaluations slower, so errors are
+                                // ignored.
+                                let _ = persistent::store(
+                                    dir,
+                                    key,
+                                    &self.globals,
+                                    &file,
+                                    expr,
+                                    &lambda,
+                                );
+                            }
+                        }
+
+                        lambda
+                    }
+                };
+
+                if let (Some(cache), Some(key)) = (&self.compilation_cache, cache_key) {
+                    cache.insert(
+                        key.clone(),
+                        &self.globals,
+                        &source,
+                        CachedCompilation {
+                            file: file.clone(),
+                            lambda: lambda.clone(),
+                            warnings: result.warnings.clone(),
+                            expr: result.expr.clone(),
+                        },
+                    );
+                }
+
+                (file, lambda)
+            }
+        };
+
+        if self.deny_deprecated {
+            deny_deprecated_syntax(&mut result.warnings, &mut result.errors, &source);
+
+            // Code using denied syntax must not be run.
+            if !result.errors.is_empty() {
+                return None;
+            }
+        }
+
+        Some((file, lambda))
+    }
+}
+
+/// Turns all warnings about deprecated syntax into errors.
+pub(crate) fn deny_deprecated_syntax(
+    warnings: &mut Vec<EvalWarning>,
+    errors: &mut Vec<Error>,
+    source: &SourceCode,
+) {
+    for warning in std::mem::take(warnings) {
+        match warning.kind.deprecated_syntax() {
+            Some(syntax) => errors.push(Error::new(
+                ErrorKind::DeprecatedSyntax(syntax),
+                warning.span,
+                source.clone(),
+            )),
+            None => warnings.push(warning),
+        }
     }
 }
 
@@ -563,6 +965,7 @@ fn parse_compile_internal(
     globals: Rc<GlobalsMap>,
     env: Option<&FxHashMap<SmolStr, Value>>,
     compiler_observer: &mut dyn CompilerObserver,
+    import_interpolated_paths: bool,
 ) -> Option<Rc<Lambda>> {
     let parsed = rnix::ast::Root::parse(code);
     let parse_errors = parsed.errors();
@@ -589,6 +992,7 @@ fn parse_compile_internal(
         &source,
         &file,
         compiler_observer,
+        import_interpolated_paths,
     ) {
         Ok(result) => result,
         Err(err) => {
@@ -7,6 +7,7 @@
 //! All methods are optional, that is, observers can implement only
 /// what they are interested in observing.
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::time::Instant;
 use tabwriter::TabWriter;
@@ -15,9 +16,11 @@ use crate::SourceCode;
 use crate::Value;
 use crate::chunk::Chunk;
 use crate::generators::VMRequest;
-use crate::opcode::{CodeIdx, Op};
+use crate::opcode::CodeIdx;
 use crate::value::Lambda;
 
+pub use crate::opcode::Op;
+
 /// Implemented by types that wish to observe internal happenings of
 /// the Tvix compiler.
 pub trait CompilerObserver {
@@ -74,6 +77,11 @@ pub trait RuntimeObserver {
     /// Called when the runtime *begins* executing an instruction. The
     /// provided stack is the state at the beginning of the operation.
     fn observe_execute_op(&mut self, _ip: CodeIdx, _: &Op, _: &[Value]) {}
+
+    /// Called when a file is imported by `import` or `scopedImport`,
+    /// or a path is imported into the store, e.g. by interpolating it
+    /// into a string.
+    fn observe_import(&mut self, _path: &Path) {}
 }
 
 #[derive(Default)]
@@ -82,6 +90,19 @@ pub struct NoOpObserver {}
 impl CompilerObserver for NoOpObserver {}
 impl RuntimeObserver for NoOpObserver {}
 
+/// An observer that records the paths of all imports in the order in
+/// which they happen, see [`RuntimeObserver::observe_import`].
+#[derive(Default)]
+pub struct ImportObserver {
+    pub imports: Vec<PathBuf>,
+}
+
+impl RuntimeObserver for ImportObserver {
+    fn observe_import(&mut self, path: &Path) {
+        self.imports.push(path.to_path_buf());
+    }
+}
+
 /// An observer that prints disassembled chunk information to its
 /// internal writer whenwever the compiler emits a toplevel function,
 /// closure or thunk.
@@ -147,6 +168,8 @@ impl<W: Write> CompilerObserver for DisassemblingObserver<W> {
 pub struct TracingObserver<W: Write> {
     // If timing is enabled, contains the timestamp of the last-emitted trace event
     last_event: Option<Instant>,
+    // If set, only these operations are traced
+    only_ops: Option<Vec<Op>>,
     writer: TabWriter<W>,
 }
 
@@ -154,6 +177,7 @@ impl<W: Write> TracingObserver<W> {
     pub fn new(writer: W) -> Self {
         Self {
             last_event: None,
+            only_ops: None,
             writer: TabWriter::new(writer),
         }
     }
@@ -163,6 +187,12 @@ impl<W: Write> TracingObserver<W> {
         self.last_event = Some(Instant::now());
     }
 
+    /// Only trace the execution of the given operations. Other runtime
+    /// events (e.g. entering call frames) are still traced.
+    pub fn only_ops(&mut self, ops: &[Op]) {
+        self.only_ops = Some(ops.to_vec());
+    }
+
     fn maybe_write_time(&mut self) {
         if let Some(last_event) = &mut self.last_event {
             let _ = write!(&mut self.writer, "+{}ns\t", last_event.elapsed().as_nanos());
@@ -305,6 +335,12 @@ impl<W: Write> RuntimeObserver for TracingObserver<W> {
     }
 
     fn observe_execute_op(&mut self, ip: CodeIdx, op: &Op, stack: &[Value]) {
+        if let Some(only_ops) = &self.only_ops {
+            if !only_ops.contains(op) {
+                return;
+            }
+        }
+
         self.maybe_write_time();
         let _ = write!(&mut self.writer, "{:04} {:?}\t", ip.0, op);
         self.write_stack(stack);
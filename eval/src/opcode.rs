@@ -59,7 +59,7 @@ pub struct UpvalueIdx(pub usize);
 /// Unless otherwise specified, operations leave their result at the
 /// top of the stack.
 #[repr(u8)]
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Op {
     /// Push a constant onto the stack.
     Constant,
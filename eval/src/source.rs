@@ -55,6 +55,11 @@ impl SourceCode {
     pub fn get_file(&self, span: Span) -> Arc<codemap::File> {
         self.codemap().look_up_span(span).file
     }
+
+    /// Returns true if both instances refer to the same codemap.
+    pub(crate) fn ptr_eq(&self, other: &SourceCode) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
 }
 
 impl Default for SourceCode {
@@ -36,3 +36,912 @@ fn skip_broken_bytecode() {
         ErrorKind::UnknownStaticVariable
     ));
 }
+
+#[test]
+fn eval_mode_strictness() {
+    // The thunk inside of the list is never forced when evaluating lazily.
+    let code = r#"[ (throw "forced") ]"#;
+
+    let result = Evaluation::builder_pure()
+        .mode(EvalMode::Lazy)
+        .build()
+        .evaluate(code, None);
+    assert!(
+        result.errors.is_empty(),
+        "lazy evaluation failed: {:?}",
+        result.errors
+    );
+    assert!(matches!(result.value, Some(Value::List(_))));
+
+    let result = Evaluation::builder_pure()
+        .mode(EvalMode::Strict)
+        .build()
+        .evaluate(code, None);
+    assert_eq!(result.errors.len(), 1);
+    assert!(matches!(
+        result.errors[0].kind,
+        ErrorKind::CatchableError(CatchableErrorKind::Throw(_))
+    ));
+}
+
+/// Counts the compiled top-level expressions.
+#[derive(Default)]
+struct CountingObserver(usize);
+
+impl observer::CompilerObserver for CountingObserver {
+    fn observe_compiled_toplevel(&mut self, _: &Rc<Lambda>) {
+        self.0 += 1;
+    }
+}
+
+#[test]
+fn compilation_cache() {
+    let code = "let x = 21; in x * 2";
+    let cache = Rc::new(CompilationCache::new());
+    let mut observer = CountingObserver::default();
+
+    let eval = Evaluation::builder_pure()
+        .with_compilation_cache(cache.clone())
+        .compiler_observer(Some(&mut observer))
+        .build();
+    let globals = eval.globals();
+    let source_map = eval.source_map();
+    let result = eval.evaluate(code, None);
+    assert!(matches!(result.value, Some(Value::Integer(42))));
+
+    // Sharing globals and source map hits the cache.
+    let result = Evaluation::builder_pure()
+        .with_globals(globals)
+        .with_source_map(source_map)
+        .with_compilation_cache(cache.clone())
+        .compiler_observer(Some(&mut observer))
+        .build()
+        .evaluate(code, None);
+    assert!(matches!(result.value, Some(Value::Integer(42))));
+    assert_eq!(observer.0, 1, "cached code should not be recompiled");
+    assert_eq!(cache.len(), 1);
+
+    // Fresh globals invalidate the cache.
+    let result = Evaluation::builder_pure()
+        .with_compilation_cache(cache.clone())
+        .compiler_observer(Some(&mut observer))
+        .build()
+        .evaluate(code, None);
+    assert!(matches!(result.value, Some(Value::Integer(42))));
+    assert_eq!(observer.0, 2, "code should be recompiled for new globals");
+}
+
+#[cfg(feature = "impure")]
+#[test]
+fn compilation_cache_reevaluates_imports() {
+    let dir = tempfile::tempdir().unwrap();
+    let entry = dir.path().join("default.nix");
+    let lib = dir.path().join("lib.nix");
+    let code = "let lib = import ./lib.nix; in lib.x";
+    let cache = Rc::new(CompilationCache::new());
+
+    std::fs::write(&lib, "{ x = 1; }").unwrap();
+    let eval = Evaluation::builder_impure()
+        .with_compilation_cache(cache.clone())
+        .build();
+    let globals = eval.globals();
+    let source_map = eval.source_map();
+    let result = eval.evaluate(code, Some(entry.clone()));
+    assert!(matches!(result.value, Some(Value::Integer(1))));
+
+    // The entry file is unchanged and hits the cache, but the import
+    // must not be reused from the previous evaluation.
+    std::fs::write(&lib, "{ x = 2; }").unwrap();
+    let result = Evaluation::builder_impure()
+        .with_globals(globals)
+        .with_source_map(source_map)
+        .with_compilation_cache(cache.clone())
+        .build()
+        .evaluate(code, Some(entry));
+    assert!(
+        matches!(result.value, Some(Value::Integer(2))),
+        "stale value from cached bytecode: {:?}",
+        result.value
+    );
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn eval_cache_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let code = r#"
+      let
+        f = { a, b ? 2 }: a * b;
+        s = "foo";
+      in [ (f { a = 21; }) (builtins.toString 15) s (map (x: x + 1) [ 1 ]) ]
+    "#;
+
+    // Each evaluation uses fresh globals and a fresh source map, like
+    // separate processes would.
+    let evaluate = |code: &str, observer: &mut CountingObserver| {
+        Evaluation::builder_pure()
+            .mode(EvalMode::Strict)
+            .eval_cache_dir(Some(dir.path().to_path_buf()))
+            .compiler_observer(Some(observer))
+            .build()
+            .evaluate(code, None)
+    };
+
+    let mut observer = CountingObserver::default();
+    let result = evaluate(code, &mut observer);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert_eq!(
+        result.value.unwrap().to_string(),
+        r#"[ 42 "15" "foo" [ 2 ] ]"#
+    );
+    assert_eq!(observer.0, 1);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    let result = evaluate(code, &mut observer);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert_eq!(
+        result.value.unwrap().to_string(),
+        r#"[ 42 "15" "foo" [ 2 ] ]"#
+    );
+    assert_eq!(observer.0, 1, "persisted code should not be recompiled");
+    assert!(result.expr.is_some());
+
+    // Globals with primitive values are inlined into the bytecode, so
+    // code referring to them is not persisted.
+    let globals = Rc::new(GlobalsMap::from_iter([("answer", Value::Integer(42))]));
+    let result = Evaluation::builder_pure()
+        .with_globals(globals)
+        .eval_cache_dir(Some(dir.path().to_path_buf()))
+        .build()
+        .evaluate("answer", None);
+    assert!(matches!(result.value, Some(Value::Integer(42))));
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn eval_cache_dir_current_time() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // `builtins.currentTime` is inlined as a constant by the compiler,
+    // so each run must see its own value rather than a persisted one.
+    let evaluate = |current_time: i64| {
+        Evaluation::builder_pure()
+            .add_builtins([("currentTime", Value::Integer(current_time))])
+            .eval_cache_dir(Some(dir.path().to_path_buf()))
+            .build()
+            .evaluate("builtins.currentTime", None)
+            .value
+    };
+
+    assert!(matches!(evaluate(1), Some(Value::Integer(1))));
+    assert!(matches!(evaluate(2), Some(Value::Integer(2))));
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn op_budget_exceeded() {
+    // Non-terminating recursion which is not caught by the thunk
+    // blackholing logic.
+    let code = "let f = n: f (n + 1); in f 0";
+
+    let result = Evaluation::builder_pure()
+        .op_budget(10_000)
+        .build()
+        .evaluate(code, None);
+
+    assert_eq!(result.errors.len(), 1);
+
+    // The error is chained through the call frames active at the time.
+    let mut kind = &result.errors[0].kind;
+    while let ErrorKind::BytecodeError(err) | ErrorKind::NativeError { err, .. } = kind {
+        kind = &err.kind;
+    }
+
+    assert!(
+        matches!(kind, ErrorKind::OpBudgetExceeded { budget: 10_000 }),
+        "unexpected error: {kind:?}"
+    );
+}
+
+#[test]
+fn op_budget_sufficient() {
+    let result = Evaluation::builder_pure()
+        .op_budget(10_000)
+        .build()
+        .evaluate("let x = 21; in x * 2", None);
+
+    assert!(
+        result.errors.is_empty(),
+        "evaluation failed: {:?}",
+        result.errors
+    );
+    assert!(matches!(result.value, Some(Value::Integer(42))));
+}
+
+#[test]
+fn max_call_depth_exceeded() {
+    let code = "let f = n: if n == 0 then 0 else 1 + f (n - 1); in f 100000";
+
+    let result = Evaluation::builder_pure()
+        .max_call_depth(1_000)
+        .build()
+        .evaluate(code, None);
+
+    assert_eq!(result.errors.len(), 1);
+    assert!(
+        matches!(
+            result.errors[0].kind,
+            ErrorKind::StackOverflow {
+                max_call_depth: 1_000
+            }
+        ),
+        "unexpected error: {:?}",
+        result.errors[0].kind
+    );
+}
+
+#[test]
+fn max_call_depth_sufficient() {
+    let code = "let f = n: if n == 0 then 0 else 1 + f (n - 1); in f 100";
+
+    let result = Evaluation::builder_pure()
+        .max_call_depth(1_000)
+        .build()
+        .evaluate(code, None);
+
+    assert!(
+        result.errors.is_empty(),
+        "evaluation failed: {:?}",
+        result.errors
+    );
+    assert!(matches!(result.value, Some(Value::Integer(100))));
+}
+
+#[test]
+fn integer_overflow() {
+    let result = Evaluation::builder_pure()
+        .build()
+        .evaluate("let x = 9223372036854775807; in x + 2", None);
+
+    assert_eq!(result.errors.len(), 1);
+
+    let mut kind = &result.errors[0].kind;
+    while let ErrorKind::BytecodeError(err) | ErrorKind::NativeError { err, .. } = kind {
+        kind = &err.kind;
+    }
+
+    assert_eq!(
+        kind.to_string(),
+        "integer overflow in 9223372036854775807 + 2"
+    );
+}
+
+#[test]
+fn deduplicated_constants() {
+    #[derive(Default)]
+    struct ConstantsObserver(usize);
+
+    impl observer::CompilerObserver for ConstantsObserver {
+        fn observe_compiled_toplevel(&mut self, lambda: &Rc<Lambda>) {
+            self.0 = lambda.chunk.constants.len();
+        }
+    }
+
+    let mut observer = ConstantsObserver::default();
+    let result = Evaluation::builder_pure()
+        .compiler_observer(Some(&mut observer))
+        .build()
+        .compile_only(r#"[ "foo" "foo" "foo" 1 1 ]"#, None);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+    // Only "foo" and 1 are stored.
+    assert_eq!(observer.0, 2);
+}
+
+#[test]
+fn legacy_let_deprecated() {
+    let result = Evaluation::builder_pure()
+        .build()
+        .compile_only("let { body = 1; }", None);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+    assert!(
+        result
+            .warnings
+            .iter()
+            .any(|w| matches!(w.kind, WarningKind::DeprecatedLegacyLet)),
+        "expected a legacy let warning, got: {:?}",
+        result.warnings
+    );
+}
+
+#[test]
+fn deny_deprecated_url_literal() {
+    let result = Evaluation::builder_pure()
+        .deny_deprecated(true)
+        .build()
+        .compile_only("http://example.com", None);
+
+    assert!(
+        result
+            .errors
+            .iter()
+            .any(|e| matches!(e.kind, ErrorKind::DeprecatedSyntax(_))),
+        "expected a deprecated syntax error, got: {:?}",
+        result.errors
+    );
+    assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+
+    let result = Evaluation::builder_pure()
+        .deny_deprecated(false)
+        .build()
+        .compile_only("http://example.com", None);
+
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert!(
+        result
+            .warnings
+            .iter()
+            .any(|w| matches!(w.kind, WarningKind::DeprecatedLiteralURL)),
+        "expected a URL literal warning, got: {:?}",
+        result.warnings
+    );
+}
+
+#[test]
+fn deny_deprecated_in_import() {
+    struct VirtualFiles;
+
+    impl ImportResolver for VirtualFiles {
+        fn resolve(&self, path: &Path) -> Option<std::io::Result<String>> {
+            (path == Path::new("/virtual/url.nix")).then(|| Ok("http://example.com".into()))
+        }
+    }
+
+    let eval = |deny_deprecated| {
+        Evaluation::builder_pure()
+            .enable_import()
+            .import_resolver(Box::new(VirtualFiles))
+            .deny_deprecated(deny_deprecated)
+            .build()
+            .evaluate("import /virtual/url.nix", None)
+    };
+
+    let result = eval(true);
+    assert!(result.value.is_none());
+    assert!(
+        result.errors.iter().any(|err| {
+            std::iter::successors(Some(err as &(dyn std::error::Error + 'static)), |err| {
+                err.source()
+            })
+            .any(|err| {
+                err.downcast_ref::<Error>()
+                    .is_some_and(|err| matches!(err.kind, ErrorKind::DeprecatedSyntax(_)))
+            })
+        }),
+        "expected a deprecated syntax error, got: {:?}",
+        result.errors
+    );
+
+    let result = eval(false);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert!(
+        result
+            .warnings
+            .iter()
+            .any(|w| matches!(w.kind, WarningKind::DeprecatedLiteralURL)),
+        "expected a URL literal warning, got: {:?}",
+        result.warnings
+    );
+}
+
+#[test]
+fn path_string_equality() {
+    let result = Evaluation::builder_pure()
+        .build()
+        .evaluate("/nix/foo == \"/nix/foo\"", None);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert!(
+        matches!(result.value, Some(Value::Bool(false))),
+        "paths must never equal strings, got {:?}",
+        result.value
+    );
+
+    let result = Evaluation::builder_pure()
+        .build()
+        .evaluate("toString /nix/foo == \"/nix/foo\"", None);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert!(
+        matches!(result.value, Some(Value::Bool(true))),
+        "a stringified path must equal its string form, got {:?}",
+        result.value
+    );
+}
+
+#[test]
+fn unused_formals() {
+    let unused_bindings = |code: &str| {
+        let result = Evaluation::builder_pure().build().compile_only(code, None);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+        result
+            .warnings
+            .iter()
+            .filter(|w| matches!(w.kind, WarningKind::UnusedBinding))
+            .count()
+    };
+
+    assert_eq!(
+        unused_bindings("{ a, b }: a"),
+        1,
+        "expected a warning for b"
+    );
+    assert_eq!(
+        unused_bindings("args@{ a, b }: a"),
+        1,
+        "expected a warning for b, but not for args"
+    );
+    assert_eq!(unused_bindings("args@{ a, _b }: a"), 0);
+}
+
+#[test]
+fn tracing_only_ops() {
+    let mut trace = vec![];
+    {
+        let mut observer = observer::TracingObserver::new(&mut trace);
+        observer.only_ops(&[observer::Op::Call, observer::Op::Force]);
+
+        let result = Evaluation::builder_pure()
+            .runtime_observer(Some(&mut observer))
+            .build()
+            .evaluate("let f = x: x + 1; in f (f 1)", None);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+    }
+
+    let trace = String::from_utf8(trace).expect("trace must be valid UTF-8");
+    let traced_ops: Vec<&str> = trace
+        .lines()
+        .filter(|line| !line.starts_with("==="))
+        .map(|line| {
+            line.split_whitespace()
+                .nth(1)
+                .expect("op lines must contain an op")
+        })
+        .collect();
+
+    assert!(traced_ops.contains(&"Call"), "no calls traced:\n{trace}");
+    assert!(
+        traced_ops.iter().all(|op| ["Call", "Force"].contains(op)),
+        "unexpected ops traced:\n{trace}"
+    );
+}
+
+#[test]
+fn hidden_global_builtins() {
+    let result = Evaluation::builder_pure()
+        .enable_import()
+        .hide_global_builtins(["import"])
+        .build()
+        .evaluate("import", None);
+    assert!(
+        result
+            .errors
+            .iter()
+            .any(|e| matches!(e.kind, ErrorKind::UnknownStaticVariable)),
+        "expected import to be unresolvable, got: {:?}",
+        result.errors
+    );
+
+    let result = Evaluation::builder_pure()
+        .enable_import()
+        .hide_global_builtins(["import"])
+        .build()
+        .evaluate("builtins.isFunction builtins.import", None);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert!(matches!(result.value, Some(Value::Bool(true))));
+}
+
+#[test]
+fn fixed_root_dir() {
+    let result = Evaluation::builder_pure()
+        .root_dir(PathBuf::from("/fixed/root"))
+        .build()
+        .evaluate("./foo/../bar", None);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert!(
+        matches!(&result.value, Some(Value::Path(p)) if p.as_path() == Path::new("/fixed/root/bar")),
+        "unexpected value: {:?}",
+        result.value
+    );
+}
+
+#[test]
+fn trivially_constant() {
+    let is_constant = |code: &str| {
+        let source = SourceCode::default();
+        let file = source.add_file("[test]".into(), code.into());
+        let expr = rnix::ast::Root::parse(code)
+            .tree()
+            .expr()
+            .expect("code must contain an expression");
+        let globals = prepare_globals(
+            vec![],
+            vec![],
+            source.clone(),
+            false,
+            None,
+            true,
+            false,
+            &[],
+        );
+
+        compile(
+            &expr,
+            None,
+            globals,
+            None,
+            &source,
+            &file,
+            &mut observer::NoOpObserver::default(),
+            true,
+        )
+        .expect("compilation must succeed")
+        .is_constant()
+    };
+
+    assert!(matches!(is_constant("42"), Some(Value::Integer(42))));
+    assert!(is_constant("1 + x").is_none());
+    assert!(is_constant("let x = 1; in 1 + x").is_none());
+}
+
+#[test]
+fn attrs_select_path() {
+    use genawaiter::rc::Gen;
+
+    // Selects `a.b.c` and `a.x.c` from its argument.
+    let select = Builtin::new("selectPath", None, 1, |mut args: Vec<Value>| {
+        Gen::new(|co| {
+            generators::pin_generator(async move {
+                let attrs = generators::request_force(&co, args.pop().unwrap()).await;
+                let attrs = attrs.to_attrs()?;
+
+                let mut results = vec![];
+                for path in [["a", "b", "c"], ["a", "x", "c"]] {
+                    let value = attrs.select_path(&co, &path).await?;
+                    results.push(value.unwrap_or(Value::Null));
+                }
+
+                Ok(Value::List(NixList::from(results)))
+            })
+        })
+    });
+
+    let result = Evaluation::builder_pure()
+        .add_builtins([("selectPath", Value::Builtin(select))])
+        .build()
+        .evaluate(
+            "builtins.selectPath { a = let inner = { b.c = 42; }; in inner; }",
+            None,
+        );
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+    let list = result.value.expect("must be some").to_list().unwrap();
+    assert!(matches!(list[0], Value::Integer(42)));
+    assert!(matches!(list[1], Value::Null));
+}
+
+#[test]
+fn duplicate_static_attrs_keys() {
+    let compile = |code: &str| Evaluation::builder_pure().build().compile_only(code, None);
+
+    for code in [
+        "{ a = 1; a = 2; }",
+        r#"{ a = 1; "a" = 2; }"#,
+        "{ a.b = 1; a = 2; }",
+        "{ inherit ({ a = 1; }) a; a = 2; }",
+    ] {
+        let result = compile(code);
+        assert!(
+            result.errors.iter().any(
+                |e| matches!(&e.kind, ErrorKind::DuplicateStaticAttrsKey { key, .. } if key == "a")
+            ),
+            "expected a duplicate key error for {code}, got: {:?}",
+            result.errors
+        );
+    }
+
+    for code in [
+        "{ a.b = 1; a.c = 2; }",
+        "{ a = { b = 1; }; a.c = 2; }",
+        r#"let k = "a"; in { ${k} = 1; a = 2; }"#,
+    ] {
+        let result = compile(code);
+        assert!(result.errors.is_empty(), "{code}: {:?}", result.errors);
+    }
+}
+
+#[test]
+fn standalone_optimiser() {
+    let optimise = |code: &str| {
+        let expr = rnix::ast::Root::parse(code)
+            .tree()
+            .expr()
+            .expect("code must contain an expression");
+        crate::optimise(&expr)
+    };
+
+    let optimisation = optimise("x || false").expect("must be optimisable");
+    assert_eq!(optimisation.replacement.to_string(), "x");
+    assert_eq!(optimisation.culprit.to_string(), "false");
+    assert!(optimisation.dead_code.is_none());
+
+    let optimisation = optimise("false && x").expect("must be optimisable");
+    assert_eq!(optimisation.replacement.to_string(), "false");
+    assert_eq!(optimisation.culprit.to_string(), "false && x");
+    assert_eq!(
+        optimisation.dead_code.map(|e| e.to_string()),
+        Some("x".into())
+    );
+
+    assert!(optimise("x || y").is_none());
+    assert!(optimise("1 + 2").is_none());
+}
+
+#[test]
+fn select_optimisation() {
+    // Selecting a statically known attribute from a global attribute
+    // set is resolved by the compiler, on the emitted bytecode.
+    let compile_constant = |code: &str| {
+        let eval = Evaluation::builder_pure().build();
+        let source = eval.source_map();
+        let file = source.add_file("[test]".into(), code.into());
+        let expr = rnix::ast::Root::parse(code)
+            .tree()
+            .expr()
+            .expect("code must contain an expression");
+
+        compile(
+            &expr,
+            None,
+            eval.globals(),
+            None,
+            &source,
+            &file,
+            &mut observer::NoOpObserver::default(),
+            true,
+        )
+        .expect("compilation must succeed")
+        .is_constant()
+    };
+
+    assert!(matches!(
+        compile_constant("builtins.length"),
+        Some(Value::Builtin(builtin)) if builtin.name() == "length"
+    ));
+    assert!(matches!(
+        compile_constant("builtins.langVersion"),
+        Some(Value::Integer(_))
+    ));
+
+    // Missing attributes are left to fail at runtime, if ever evaluated.
+    assert!(compile_constant("builtins.doesNotExist").is_none());
+    assert!(compile_constant("(builtins // { }).length").is_none());
+}
+
+#[test]
+fn import_resolver() {
+    struct VirtualFiles;
+
+    impl ImportResolver for VirtualFiles {
+        fn resolve(&self, path: &Path) -> Option<std::io::Result<String>> {
+            match path.to_str()? {
+                "/virtual/answer.nix" => Some(Ok("import /virtual/default.nix + 2".into())),
+                "/virtual/default.nix" => Some(Ok("40".into())),
+                _ => None,
+            }
+        }
+    }
+
+    let eval = || {
+        Evaluation::builder_pure()
+            .enable_import()
+            .import_resolver(Box::new(VirtualFiles))
+            .build()
+    };
+
+    let result = eval().evaluate("import /virtual/answer.nix", None);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert!(matches!(result.value, Some(Value::Integer(42))));
+
+    // Unresolved paths are read through the IO handle, which fails for DummyIO.
+    let result = eval().evaluate("import /virtual/missing.nix", None);
+    assert!(!result.errors.is_empty());
+}
+
+#[test]
+fn observe_imports() {
+    struct VirtualFiles;
+
+    impl ImportResolver for VirtualFiles {
+        fn resolve(&self, path: &Path) -> Option<std::io::Result<String>> {
+            match path.to_str()? {
+                "/virtual/a.nix" => Some(Ok("1".into())),
+                "/virtual/b.nix" => Some(Ok("x + 1".into())),
+                _ => None,
+            }
+        }
+    }
+
+    let mut observer = observer::ImportObserver::default();
+    let result = Evaluation::builder_pure()
+        .enable_import()
+        .import_resolver(Box::new(VirtualFiles))
+        .runtime_observer(Some(&mut observer))
+        .build()
+        .evaluate(
+            "import /virtual/a.nix + scopedImport { x = 40; } /virtual/b.nix",
+            None,
+        );
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert!(matches!(result.value, Some(Value::Integer(42))));
+
+    assert_eq!(
+        observer.imports,
+        vec![
+            PathBuf::from("/virtual/a.nix"),
+            PathBuf::from("/virtual/b.nix")
+        ]
+    );
+}
+
+#[test]
+fn deep_force() {
+    let eval = Evaluation::builder_pure().build();
+    let globals = eval.globals();
+    let source = eval.source_map();
+
+    let result = eval.evaluate("{ a = { b = 1 + 1; }; c = [ (2 * 3) ]; }", None);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    let value = result.value.expect("must have a value");
+
+    let result = Evaluation::builder_pure()
+        .with_globals(globals.clone())
+        .with_source_map(source.clone())
+        .build()
+        .deep_force(value);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+    // All nested thunks must have been evaluated.
+    let forced = |value: &Value| match value {
+        Value::Thunk(thunk) => {
+            assert!(thunk.is_evaluated(), "thunk was not forced");
+            thunk.value().clone()
+        }
+        value => value.clone(),
+    };
+    let value = result.value.expect("must have a value");
+    let a = forced(value.to_attrs().unwrap().select_str("a").unwrap());
+    let b = forced(a.to_attrs().unwrap().select_str("b").unwrap());
+    assert!(matches!(b, Value::Integer(2)));
+    let c = forced(value.to_attrs().unwrap().select_str("c").unwrap());
+    let c0 = forced(&c.to_list().unwrap()[0]);
+    assert!(matches!(c0, Value::Integer(6)));
+
+    // Errors nested in the value are only encountered when deep forcing.
+    let result = Evaluation::builder_pure()
+        .with_globals(globals.clone())
+        .with_source_map(source.clone())
+        .build()
+        .evaluate(r#"{ a = throw "nope"; }"#, None);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+    let result = Evaluation::builder_pure()
+        .with_globals(globals)
+        .with_source_map(source)
+        .build()
+        .deep_force(result.value.expect("must have a value"));
+    assert!(result.value.is_none());
+    assert_eq!(result.errors.len(), 1);
+}
+
+#[test]
+fn import_interpolated_paths() {
+    /// Records imported paths, importing every path at the same
+    /// (made-up) store path.
+    #[derive(Default)]
+    struct RecordingIO(std::cell::RefCell<Vec<PathBuf>>);
+
+    impl EvalIO for RecordingIO {
+        fn path_exists(&self, path: &Path) -> std::io::Result<bool> {
+            DummyIO.path_exists(path)
+        }
+
+        fn open(&self, path: &Path) -> std::io::Result<Box<dyn std::io::Read>> {
+            DummyIO.open(path)
+        }
+
+        fn file_type(&self, path: &Path) -> std::io::Result<FileType> {
+            DummyIO.file_type(path)
+        }
+
+        fn read_dir(&self, path: &Path) -> std::io::Result<Vec<(bytes::Bytes, FileType)>> {
+            DummyIO.read_dir(path)
+        }
+
+        fn import_path(&self, path: &Path) -> std::io::Result<PathBuf> {
+            self.0.borrow_mut().push(path.to_owned());
+            Ok(PathBuf::from(
+                "/nix/store/00000000000000000000000000000000-foo",
+            ))
+        }
+    }
+
+    struct VirtualFiles;
+
+    impl ImportResolver for VirtualFiles {
+        fn resolve(&self, path: &Path) -> Option<std::io::Result<String>> {
+            (path == Path::new("/root/lib.nix")).then(|| Ok(r#""${./foo}""#.into()))
+        }
+    }
+
+    let eval = |code: &str, import_interpolated_paths| {
+        let io = Rc::new(RecordingIO::default());
+        let result = Evaluation::builder(io.clone())
+            .enable_import()
+            .import_resolver(Box::new(VirtualFiles))
+            .root_dir(PathBuf::from("/root"))
+            .import_interpolated_paths(import_interpolated_paths)
+            .build()
+            .evaluate(code, None);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+        let imported = io.0.take();
+        let value = result.value.unwrap().to_str().unwrap();
+        (
+            String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            imported,
+        )
+    };
+
+    // The setting applies to imported files as well.
+    for code in [r#""${./foo}""#, "import ./lib.nix"] {
+        assert_eq!(
+            eval(code, true),
+            (
+                "/nix/store/00000000000000000000000000000000-foo".to_string(),
+                vec![PathBuf::from("/root/foo")]
+            ),
+            "{code}"
+        );
+        assert_eq!(
+            eval(code, false),
+            ("/root/foo".to_string(), vec![]),
+            "{code}"
+        );
+    }
+}
+
+#[test]
+fn error_diagnostics() {
+    let code = "let x = 1; y = 2; in x - true";
+    let eval = Evaluation::builder_pure().build();
+    let source = eval.source_map();
+    let result = eval.evaluate(code, None);
+    assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+
+    let diagnostic = result.errors[0].to_diagnostic();
+    assert_eq!(diagnostic.file, "[code]");
+    assert_eq!(&code[diagnostic.start..diagnostic.end], "x - true");
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.code, "E006");
+
+    let json = serde_json::to_value(&diagnostic).unwrap();
+    assert_eq!(json["severity"], "error");
+    assert_eq!(json["start"], diagnostic.start);
+
+    assert_eq!(result.warnings.len(), 1, "{:?}", result.warnings);
+    let diagnostic = result.warnings[0].to_diagnostic(&source);
+    assert_eq!(&code[diagnostic.start..diagnostic.end], "y");
+    assert_eq!(diagnostic.severity, Severity::Warning);
+    assert_eq!(diagnostic.code, "W003");
+}
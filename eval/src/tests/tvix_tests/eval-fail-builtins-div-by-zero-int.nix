@@ -0,0 +1 @@
+builtins.div 1 0
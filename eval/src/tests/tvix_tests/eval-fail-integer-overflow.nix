@@ -0,0 +1 @@
+9223372036854775807 * 2
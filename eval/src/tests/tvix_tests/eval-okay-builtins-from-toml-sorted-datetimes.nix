@@ -0,0 +1,21 @@
+let
+  parsed = builtins.fromTOML ''
+    zeta = 1
+    alpha = 2
+
+    [outer.zulu]
+    yankee = 1
+    xray = 2
+
+    [outer.bravo.charlie]
+    delta = 1979-05-27T07:32:00Z
+    alpha = 1979-05-27
+    local = 07:32:00
+  '';
+in
+[
+  (builtins.attrNames parsed)
+  (builtins.attrNames parsed.outer)
+  (builtins.attrNames parsed.outer.zulu)
+  parsed.outer.bravo.charlie
+]
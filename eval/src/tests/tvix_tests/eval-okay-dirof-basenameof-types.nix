@@ -0,0 +1,9 @@
+# dirOf preserves whether its argument is a path, baseNameOf always
+# returns a string.
+[
+  (builtins.typeOf (dirOf ./a/b))
+  (builtins.typeOf (dirOf "a/b"))
+  (builtins.typeOf (dirOf /.))
+  (builtins.typeOf (baseNameOf ./a/b))
+  (builtins.typeOf (baseNameOf "a/b"))
+]
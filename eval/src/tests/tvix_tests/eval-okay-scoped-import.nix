@@ -0,0 +1,5 @@
+[
+  (scopedImport { x = 1; } ./scoped-import.nix)
+  (scopedImport { x = 2; } ./scoped-import.nix)
+  (builtins.scopedImport { x = 3; true = false; } ./scoped-import.nix)
+]
@@ -16,6 +16,7 @@ use super::string::NixString;
 use super::thunk::ThunkSet;
 use crate::CatchableErrorKind;
 use crate::errors::ErrorKind;
+use crate::vm::generators::{self, GenCo};
 
 #[cfg(test)]
 mod tests;
@@ -167,6 +168,30 @@ impl NixAttrs {
             })
     }
 
+    /// Select a value from nested attribute sets by following the given
+    /// path of keys, forcing intermediate values along the way. Returns
+    /// `None` if any segment of the path is missing.
+    ///
+    /// Catchable errors encountered while forcing an intermediate value
+    /// are returned as the selected value.
+    pub async fn select_path(&self, co: &GenCo, path: &[&str]) -> Result<Option<Value>, ErrorKind> {
+        let mut value = Value::attrs(self.clone());
+
+        for key in path {
+            let attrs = generators::request_force(co, value).await;
+            if attrs.is_catchable() {
+                return Ok(Some(attrs));
+            }
+
+            match attrs.to_attrs()?.select_str(key) {
+                Some(next) => value = next.clone(),
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(value))
+    }
+
     pub fn contains<Q>(&self, key: &Q) -> bool
     where
         NixString: Borrow<Q>,
@@ -182,7 +207,12 @@ impl NixAttrs {
     }
 
     /// Construct an iterator over all the key-value pairs in lexicographic
-    /// order of their keys.
+    /// order of their keys, i.e. the order in which Nix presents attribute
+    /// sets (e.g. in `builtins.attrNames`).
+    ///
+    /// The iteration order of [Self::iter] is arbitrary, so this should be
+    /// used whenever the order is observable. Sorting happens when the
+    /// iterator is constructed and takes O(n log n).
     pub fn iter_sorted(&self) -> Iter<KeyValue<'_>> {
         let sorted = self.0.iter().sorted_by_key(|x| x.0);
         Iter(KeyValue::Sorted(sorted))
@@ -205,8 +235,8 @@ impl NixAttrs {
         Keys(KeysInner::Map(self.0.keys()))
     }
 
-    /// Same as [Self::keys], but marks call sites which rely on the
-    /// iteration being lexicographic.
+    /// Same as [Self::keys], but iterates in lexicographic order, like
+    /// [Self::iter_sorted].
     pub fn keys_sorted(&self) -> Keys<'_> {
         Keys(KeysInner::Sorted(self.0.keys().sorted()))
     }
@@ -20,3 +20,30 @@ fn test_map_attrs_iter() {
     assert_eq!(v.to_str().unwrap(), B("value"));
     assert!(iter.next().is_none());
 }
+
+#[test]
+fn test_sorted_attrs_iter() {
+    let attrs = NixAttrs::from_iter(
+        ["c", "a", "B", "b", "aa"]
+            .into_iter()
+            .map(|k| (k, Value::from(k))),
+    );
+
+    let expected = [B("B"), B("a"), B("aa"), B("b"), B("c")];
+
+    let keys = attrs
+        .keys_sorted()
+        .map(|k| k.as_bytes())
+        .collect::<Vec<_>>();
+    assert_eq!(keys, expected);
+
+    let pairs = attrs
+        .iter_sorted()
+        .map(|(k, v)| (k.as_bytes(), v.to_str().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(pairs.len(), expected.len());
+    for ((k, v), expected) in pairs.into_iter().zip(expected) {
+        assert_eq!(k, expected);
+        assert_eq!(v.as_bytes(), expected);
+    }
+}
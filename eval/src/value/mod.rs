@@ -221,6 +221,36 @@ pub enum PointerEquality {
     AllowAll,
 }
 
+/// Type of a [`Value`], at a finer granularity than the Nix type names
+/// returned by [`Value::type_of`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DetailedType {
+    Null,
+    Bool,
+    Int,
+    Float,
+
+    /// String without any context.
+    String,
+
+    /// String carrying context, e.g. references to store paths.
+    ContextfulString,
+
+    Path,
+    Attrs,
+    List,
+
+    /// Function defined in Nix code.
+    Closure,
+
+    /// Builtin function, possibly partially applied.
+    Builtin,
+
+    /// Internal value which is never returned to users, see
+    /// [`Value::type_of`] for its name.
+    Internal,
+}
+
 impl Value {
     /// Construct a [`Value::Attrs`] from a [`NixAttrs`].
     pub fn attrs(attrs: NixAttrs) -> Self {
@@ -483,7 +513,12 @@ impl Value {
     /// The `top_level` parameter controls whether this invocation is the top-level
     /// comparison, or a nested value comparison. See
     /// `//tvix/docs/value-pointer-equality.md`
-    pub(crate) async fn nix_eq(
+    ///
+    /// This implements the semantics of the `==` operator; use
+    /// [`PointerEquality::ForbidAll`] to replicate it exactly. No coercions
+    /// take place, so e.g. a [`Value::Path`] is never equal to a
+    /// [`Value::String`], even if the string holds the same path.
+    pub async fn nix_eq(
         self,
         other: Value,
         co: &GenCo,
@@ -681,6 +716,32 @@ impl Value {
         }
     }
 
+    /// Returns the [`DetailedType`] of this value, which e.g. tells
+    /// strings with context apart from ones without.
+    pub fn detailed_type(&self) -> DetailedType {
+        match self {
+            Value::Null => DetailedType::Null,
+            Value::Bool(_) => DetailedType::Bool,
+            Value::Integer(_) => DetailedType::Int,
+            Value::Float(_) => DetailedType::Float,
+            Value::String(s) if s.has_context() => DetailedType::ContextfulString,
+            Value::String(_) => DetailedType::String,
+            Value::Path(_) => DetailedType::Path,
+            Value::Attrs(_) => DetailedType::Attrs,
+            Value::List(_) => DetailedType::List,
+            Value::Closure(_) => DetailedType::Closure,
+            Value::Builtin(_) => DetailedType::Builtin,
+
+            Value::Thunk(_)
+            | Value::AttrNotFound
+            | Value::Blueprint(_)
+            | Value::DeferredUpvalue(_)
+            | Value::UnresolvedPath(_)
+            | Value::FinaliseRequest(_)
+            | Value::Catchable(_) => DetailedType::Internal,
+        }
+    }
+
     gen_cast!(as_bool, bool, "bool", Value::Bool(b), *b);
     gen_cast!(as_int, i64, "int", Value::Integer(x), *x);
     gen_cast!(as_float, f64, "float", Value::Float(x), *x);
@@ -1041,6 +1102,21 @@ mod tests {
         assert_eq!(size_of::<Value>(), 16);
     }
 
+    #[test]
+    fn detailed_type() {
+        assert_eq!(Value::Integer(42).detailed_type(), DetailedType::Int);
+        assert_eq!(Value::Float(4.2).detailed_type(), DetailedType::Float);
+        assert_eq!(Value::from("foo").detailed_type(), DetailedType::String);
+
+        let context = NixContext::from(NixContextElement::Plain(
+            "/nix/store/00000000000000000000000000000000-foo".into(),
+        ));
+        assert_eq!(
+            Value::String(NixString::new_context_from(context, "foo")).detailed_type(),
+            DetailedType::ContextfulString
+        );
+    }
+
     mod floats {
         use crate::value::total_fmt_float;
 
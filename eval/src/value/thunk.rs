@@ -319,6 +319,15 @@ impl Thunk {
         )
     }
 
+    /// Returns the lambda and span of a suspended thunk which has not
+    /// been forced yet, e.g. one emitted as a constant by the compiler.
+    pub(crate) fn suspended_lambda(&self) -> Option<(Rc<Lambda>, Span)> {
+        match &*self.0.borrow() {
+            ThunkRepr::Suspended { lambda, span, .. } => Some((lambda.clone(), *span)),
+            _ => None,
+        }
+    }
+
     /// Returns true if forcing this thunk will not change it.
     pub fn is_forced(&self) -> bool {
         self.0.borrow().is_forced()
@@ -425,4 +434,10 @@ impl ThunkSet {
         let ptr: *const ThunkRepr = thunk.0.as_ptr();
         self.0.insert(ptr)
     }
+
+    /// Check whether the given thunk has already been seen, without
+    /// marking it.
+    pub fn contains(&self, thunk: &Thunk) -> bool {
+        self.0.contains(&thunk.0.as_ptr().cast_const())
+    }
 }
@@ -102,6 +102,10 @@ pub enum VMRequest {
     /// Request that the VM imports the given path through its I/O interface.
     PathImport(PathBuf),
 
+    /// Inform the VM that the Nix file at the given path is being
+    /// imported, e.g. by `import` or `scopedImport`.
+    RecordImport(PathBuf),
+
     /// Request that the VM opens the specified file and provides a reader.
     OpenFile(PathBuf),
 
@@ -169,6 +173,7 @@ impl Display for VMRequest {
                 write!(f, "import_cache_put({})", p.to_string_lossy())
             }
             VMRequest::PathImport(p) => write!(f, "path_import({})", p.to_string_lossy()),
+            VMRequest::RecordImport(p) => write!(f, "record_import({})", p.to_string_lossy()),
             VMRequest::OpenFile(p) => {
                 write!(f, "open_file({})", p.to_string_lossy())
             }
@@ -415,6 +420,8 @@ impl VM<'_> {
                         }
 
                         VMRequest::PathImport(path) => {
+                            self.observer.observe_import(&path);
+
                             let imported = self
                                 .io_handle
                                 .as_ref()
@@ -428,6 +435,11 @@ impl VM<'_> {
                             message = VMResponse::Path(imported);
                         }
 
+                        VMRequest::RecordImport(path) => {
+                            self.observer.observe_import(&path);
+                            message = VMResponse::Empty;
+                        }
+
                         VMRequest::OpenFile(path) => {
                             let reader = self
                                 .io_handle
@@ -697,6 +709,14 @@ pub(crate) async fn request_path_import(co: &GenCo, path: PathBuf) -> PathBuf {
     }
 }
 
+/// Inform the VM that the Nix file at the given path is being imported.
+pub(crate) async fn request_record_import(co: &GenCo, path: PathBuf) {
+    match co.yield_(VMRequest::RecordImport(path)).await {
+        VMResponse::Empty => {}
+        msg => panic!("Tvix bug: VM responded with incorrect generator message: {msg}"),
+    }
+}
+
 /// Request that the VM open a [std::io::Read] for the specified file.
 pub async fn request_open_file(co: &GenCo, path: PathBuf) -> Box<dyn std::io::Read> {
     match co.yield_(VMRequest::OpenFile(path)).await {
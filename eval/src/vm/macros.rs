@@ -6,6 +6,25 @@
 /// types.
 #[macro_export]
 macro_rules! arithmetic_op {
+    // Integer arithmetic is checked, reporting the operands on overflow.
+    (@int $i1:expr, $i2:expr, +) => { arithmetic_op!(@checked $i1, $i2, +, checked_add) };
+    (@int $i1:expr, $i2:expr, -) => { arithmetic_op!(@checked $i1, $i2, -, checked_sub) };
+    (@int $i1:expr, $i2:expr, *) => { arithmetic_op!(@checked $i1, $i2, *, checked_mul) };
+    (@int $i1:expr, $i2:expr, /) => {{
+        if $i2 == 0 {
+            Err(ErrorKind::DivisionByZero)
+        } else {
+            arithmetic_op!(@checked $i1, $i2, /, checked_div)
+        }
+    }};
+
+    (@checked $i1:expr, $i2:expr, $op:tt, $checked:ident) => {{
+        let (lhs, rhs): (i64, i64) = ($i1, $i2);
+        lhs.$checked(rhs)
+            .map(Value::Integer)
+            .ok_or(ErrorKind::IntegerOverflow { op: stringify!($op), lhs, rhs })
+    }};
+
     ( $self:ident, $op:tt ) => {{ // TODO: remove
         let b = $self.pop();
         let a = $self.pop();
@@ -15,7 +34,7 @@ macro_rules! arithmetic_op {
 
     ( $a:expr, $b:expr, $op:tt ) => {{
         match ($a, $b) {
-            (Value::Integer(i1), Value::Integer(i2)) => Ok(Value::Integer(i1 $op i2)),
+            (Value::Integer(i1), Value::Integer(i2)) => arithmetic_op!(@int *i1, *i2, $op),
             (Value::Float(f1), Value::Float(f2)) => Ok(Value::Float(f1 $op f2)),
             (Value::Integer(i1), Value::Float(f2)) => Ok(Value::Float(*i1 as f64 $op f2)),
             (Value::Float(f1), Value::Integer(i2)) => Ok(Value::Float(f1 $op *i2 as f64)),
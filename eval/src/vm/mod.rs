@@ -345,9 +345,19 @@ struct VM<'o> {
     /// Control is yielded to the outer VM loop, which evaluates the next frame
     /// and returns the result itself to the `builtins.tryEval` frame.
     try_eval_frames: Vec<usize>,
+
+    /// Maximum number of operations this VM may execute, if limited.
+    op_budget: Option<u64>,
+
+    /// Number of operations executed so far.
+    ops_executed: u64,
+
+    /// Maximum nesting depth of frames on the frame stack, if limited.
+    max_call_depth: Option<usize>,
 }
 
 impl<'o> VM<'o> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         nix_search_path: NixSearchPath,
         io_handle: Rc<dyn EvalIO>,
@@ -355,6 +365,8 @@ impl<'o> VM<'o> {
         source: SourceCode,
         globals: Rc<GlobalsMap>,
         reasonable_span: Span,
+        op_budget: Option<u64>,
+        max_call_depth: Option<usize>,
     ) -> Self {
         Self {
             nix_search_path,
@@ -369,6 +381,9 @@ impl<'o> VM<'o> {
             warnings: vec![],
             import_cache: Default::default(),
             try_eval_frames: vec![],
+            op_budget,
+            ops_executed: 0,
+            max_call_depth,
         }
     }
 
@@ -386,6 +401,19 @@ impl<'o> VM<'o> {
 
             match frame {
                 Frame::CallFrame { call_frame, span } => {
+                    // The frame stack lives on the heap, so this only
+                    // guards against runaway recursion exhausting memory
+                    // or taking forever.
+                    if let Some(max_call_depth) = self.max_call_depth {
+                        if frame_id >= max_call_depth {
+                            return Err(Error::new(
+                                ErrorKind::StackOverflow { max_call_depth },
+                                span,
+                                self.source.clone(),
+                            ));
+                        }
+                    }
+
                     self.observer
                         .observe_enter_call_frame(0, &call_frame.lambda, frame_id);
 
@@ -460,6 +488,13 @@ impl<'o> VM<'o> {
             let op = frame.inc_ip();
             self.observer.observe_execute_op(frame.ip, &op, &self.stack);
 
+            self.ops_executed += 1;
+            if let Some(budget) = self.op_budget {
+                if self.ops_executed > budget {
+                    return frame.error(self, ErrorKind::OpBudgetExceeded { budget });
+                }
+            }
+
             match op {
                 Op::ThunkSuspended | Op::ThunkClosure => {
                     let idx = frame.read_uvarint() as usize;
@@ -1368,17 +1403,24 @@ async fn final_deep_force(co: GenCo) -> Result<Value, ErrorKind> {
 }
 
 /// Specification for how to handle top-level values returned by evaluation
+///
+/// The mode only affects the value returned from the top-level expression. Evaluation of the
+/// expression itself is lazy in both modes, as required by the Nix language.
 #[derive(Debug, Clone, Copy, Default)]
 pub enum EvalMode {
     /// The default. Values are returned from evaluations as-is, without any extra forcing or
-    /// special handling.
+    /// special handling. Only the outermost value is forced, so e.g. list elements or attribute
+    /// values of the result may still be unevaluated thunks, and errors within them go unnoticed.
     #[default]
     Lazy,
 
-    /// Strictly and deeply evaluate top-level values returned by evaluation.
+    /// Strictly and deeply evaluate top-level values returned by evaluation. Errors anywhere in
+    /// the result (e.g. in a nested attribute value) fail the evaluation, like they would with
+    /// `nix-instantiate --eval --strict`.
     Strict,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn run_lambda(
     nix_search_path: NixSearchPath,
     io_handle: Rc<dyn EvalIO>,
@@ -1387,6 +1429,8 @@ pub fn run_lambda(
     globals: Rc<GlobalsMap>,
     lambda: Rc<Lambda>,
     mode: EvalMode,
+    op_budget: Option<u64>,
+    max_call_depth: Option<usize>,
 ) -> EvalResult<RuntimeResult> {
     // Retain the top-level span of the expression in this lambda, as
     // synthetic "calls" in deep_force will otherwise not have a span
@@ -1403,6 +1447,8 @@ pub fn run_lambda(
         source,
         globals,
         root_span,
+        op_budget,
+        max_call_depth,
     );
 
     // When evaluating strictly, synthesise a frame that will instruct
@@ -1424,3 +1470,34 @@ pub fn run_lambda(
 
     vm.execute()
 }
+
+/// Deeply force the given value, e.g. one returned from a previous lazy
+/// evaluation, reporting errors without a more specific location at the
+/// given span.
+#[allow(clippy::too_many_arguments)]
+pub fn run_deep_force(
+    nix_search_path: NixSearchPath,
+    io_handle: Rc<dyn EvalIO>,
+    observer: &mut dyn RuntimeObserver,
+    source: SourceCode,
+    globals: Rc<GlobalsMap>,
+    value: Value,
+    span: Span,
+    op_budget: Option<u64>,
+    max_call_depth: Option<usize>,
+) -> EvalResult<RuntimeResult> {
+    let mut vm = VM::new(
+        nix_search_path,
+        io_handle,
+        observer,
+        source,
+        globals,
+        span,
+        op_budget,
+        max_call_depth,
+    );
+
+    vm.stack.push(value);
+    vm.enqueue_generator("final_deep_force", span, final_deep_force);
+    vm.execute()
+}
@@ -4,8 +4,9 @@
 use codemap_diagnostic::{ColorConfig, Diagnostic, Emitter, Level, SpanLabel, SpanStyle};
 
 use crate::SourceCode;
+use crate::diagnostic::Severity;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum WarningKind {
     DeprecatedLiteralURL,
     UselessInherit,
@@ -25,7 +26,19 @@ pub enum WarningKind {
     NotImplemented(&'static str),
 }
 
-#[derive(Debug)]
+impl WarningKind {
+    /// Returns a description of the deprecated syntax this warning is
+    /// about, if any.
+    pub(crate) fn deprecated_syntax(&self) -> Option<&'static str> {
+        match self {
+            WarningKind::DeprecatedLiteralURL => Some("URL literals"),
+            WarningKind::DeprecatedLegacyLet => Some("legacy `let` expressions"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct EvalWarning {
     pub kind: WarningKind,
     pub span: codemap::Span,
@@ -49,6 +62,17 @@ impl EvalWarning {
             .emit(&[self.diagnostic(source)]);
     }
 
+    /// Create a machine-readable description of this warning.
+    pub fn to_diagnostic(&self, source: &SourceCode) -> crate::diagnostic::Diagnostic {
+        crate::diagnostic::Diagnostic::new(
+            source,
+            self.span,
+            Severity::Warning,
+            self.message(source),
+            self.code(),
+        )
+    }
+
     /// Create the optional span label displayed as an annotation on
     /// the underlined span of the warning.
     fn span_label(&self) -> Option<String> {
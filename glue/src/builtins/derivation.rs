@@ -469,7 +469,8 @@ pub(crate) mod derivation_builtins {
                 *known_paths
                     .get_hash_derivation_modulo(&drv_path.to_owned())
                     .unwrap_or_else(|| panic!("{drv_path} not found"))
-            }),
+            })
+            .map_err(DerivationError::InvalidDerivation)?,
         )
         .map_err(DerivationError::InvalidDerivation)?;
 
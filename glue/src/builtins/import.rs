@@ -172,7 +172,7 @@ mod import_builtins {
 
                 // If an expected hash was provided upfront, compare and bail out.
                 if let Some(expected_sha256) = expected_sha256 {
-                    if actual_sha256 != expected_sha256 {
+                    if !NixHash::Sha256(actual_sha256).ct_eq(&NixHash::Sha256(expected_sha256)) {
                         return Err(ImportError::HashMismatch(
                             path.clone(),
                             NixHash::Sha256(expected_sha256),
@@ -192,9 +192,11 @@ mod import_builtins {
                     .follow_root_links(false)
                     .into_iter();
 
-                state
-                    .simulated_store
-                    .import_path_by_entries(&name, dir_entry, expected_sha256)?
+                state.simulated_store.import_path_by_entries(
+                    &name,
+                    dir_entry,
+                    expected_sha256.map(|digest| CAHash::Nar(NixHash::Sha256(digest))),
+                )?
             }
 
             FileType::Directory if !recursive_ingestion => {
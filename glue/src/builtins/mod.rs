@@ -66,7 +66,7 @@ mod tests {
     use crate::tvix_store_io::TvixStoreIO;
 
     use super::{add_derivation_builtins, add_import_builtins};
-    use nix_compat::store_path::hash_placeholder;
+    use nix_compat::store_path::{StorePath, hash_placeholder};
     use rstest::rstest;
     use tempfile::TempDir;
     use tvix_eval::{EvalIO, EvaluationResult};
@@ -104,6 +104,60 @@ mod tests {
         }
     }
 
+    /// Store paths serialised by `builtins.toJSON` keep their context, so they
+    /// become inputs of derivations using the JSON.
+    #[test]
+    fn derivation_to_json_context() {
+        let temp = TempDir::new().expect("create temporary directory");
+        let file = temp.path().join("file");
+        fs::write(&file, "hello").expect("creating file");
+
+        let io = Rc::new(TvixStoreIO::new(Default::default()));
+        let eval_builder = tvix_eval::Evaluation::builder(io.clone() as Rc<dyn EvalIO>);
+        let result = add_derivation_builtins(eval_builder, Rc::clone(&io))
+            .build()
+            .evaluate(
+                format!(
+                    r#"(derivation {{ name = "foo"; builder = "/bin/sh"; system = "x86_64-linux"; json = builtins.toJSON {{ x = {}; }}; }}).drvPath"#,
+                    file.display()
+                ),
+                None,
+            );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+        let value = result.value.expect("must be some");
+        let drv_path = StorePath::<String>::from_absolute_path(&value.to_str().unwrap())
+            .expect("drvPath must be a store path");
+        let imported = io.import_path(&file).expect("importing must succeed");
+        let imported =
+            StorePath::<String>::from_absolute_path(imported.as_os_str().as_encoded_bytes())
+                .expect("imported path must be a store path");
+
+        let known_paths = io.known_paths.borrow();
+        let drv = known_paths
+            .get_drv_by_drvpath(&drv_path)
+            .expect("derivation must be known");
+        assert!(
+            drv.input_sources.contains(&imported),
+            "{imported} missing from input sources {:?}",
+            drv.input_sources
+        );
+    }
+
+    #[test]
+    fn builtin_names() {
+        let io = Rc::new(TvixStoreIO::new(Default::default()));
+        let eval_builder = tvix_eval::Evaluation::builder(io.clone() as Rc<dyn EvalIO>);
+        let names = add_derivation_builtins(eval_builder, io)
+            .enable_import()
+            .build()
+            .builtin_names();
+
+        assert!(names.iter().any(|name| name == "derivation"), "{names:?}");
+        assert!(names.iter().any(|name| name == "import"), "{names:?}");
+        assert!(names.is_sorted(), "{names:?}");
+    }
+
     /// a derivation with an empty name is an error.
     #[test]
     fn derivation_empty_name_fail() {
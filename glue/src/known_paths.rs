@@ -74,11 +74,13 @@ impl KnownPaths {
         }
 
         // compute the hash derivation modulo
-        let hash_derivation_modulo = drv.hash_derivation_modulo(|drv_path| {
-            self.get_hash_derivation_modulo(&drv_path.to_owned())
-                .unwrap_or_else(|| panic!("{drv_path} not found"))
-                .to_owned()
-        });
+        let hash_derivation_modulo = drv
+            .hash_derivation_modulo(|drv_path| {
+                self.get_hash_derivation_modulo(&drv_path.to_owned())
+                    .unwrap_or_else(|| panic!("{drv_path} not found"))
+                    .to_owned()
+            })
+            .expect("derivation must pass validation");
 
         // For all output paths, update our lookup table.
         // We only write into the lookup table once.
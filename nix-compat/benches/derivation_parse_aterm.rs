@@ -7,7 +7,10 @@ use nix_compat::derivation::Derivation;
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
-const RESOURCES_PATHS: &str = "src/derivation/tests/derivation_tests/ok";
+const RESOURCES_PATHS: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/src/derivation/tests/derivation_tests/ok"
+);
 
 fn bench_aterm_parser(c: &mut Criterion) {
     for drv in [
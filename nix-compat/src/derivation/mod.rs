@@ -1,9 +1,10 @@
 use crate::store_path::{
     self, StorePath, StorePathRef, build_ca_path, build_output_path, build_text_path,
 };
-use bstr::BString;
+use bstr::{BString, ByteSlice};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet};
 use std::io;
 
@@ -116,18 +117,70 @@ impl Derivation {
         buffer
     }
 
+    /// Returns a human-readable rendering of the ATerm serialization, with
+    /// labelled fields and one element per line. This is meant for displaying
+    /// and diffing derivations.
+    ///
+    /// The result is *not* valid ATerm, and can't be parsed back. It must not
+    /// be used for hashing or path calculation, use
+    /// [Derivation::to_aterm_bytes] for that.
+    pub fn to_pretty_aterm(&self) -> String {
+        let mut s = String::new();
+
+        // Writing to a String can't fail.
+        write::write_pretty(&mut s, self).unwrap();
+
+        s
+    }
+
     /// Parse an Derivation in ATerm serialization, and validate it passes our
     /// set of validations.
     pub fn from_aterm_bytes(b: &[u8]) -> Result<Derivation, parser::Error<&[u8]>> {
         parser::parse(b)
     }
 
+    /// Parse a Derivation in ATerm serialization like
+    /// [Derivation::from_aterm_bytes], but tolerate trailing garbage after
+    /// it, which is returned alongside the Derivation.
+    ///
+    /// This is useful to recover data from slightly corrupt `.drv` files.
+    pub fn from_aterm_bytes_lenient(
+        b: &[u8],
+    ) -> Result<(Derivation, Vec<u8>), parser::Error<&[u8]>> {
+        parser::parse_lenient(b).map(|(derivation, rest)| (derivation, rest.to_vec()))
+    }
+
+    /// Returns the references of the derivation (file) itself, as a sorted set
+    /// of absolute store paths.
+    ///
+    /// These are all [Derivation::input_sources] and keys of
+    /// [Derivation::input_derivations].
+    pub fn reference_paths(&self) -> BTreeSet<String> {
+        self.input_sources
+            .iter()
+            .chain(self.input_derivations.keys())
+            .map(StorePath::to_absolute_path)
+            .collect()
+    }
+
+    /// Returns the raw bytes of the environment variable with the given name.
+    pub fn env_bytes(&self, key: &str) -> Option<&[u8]> {
+        self.environment.get(key).map(|v| v.as_slice())
+    }
+
+    /// Returns the environment variable with the given name as a string.
+    ///
+    /// Values are not necessarily valid UTF-8, invalid sequences are replaced
+    /// with U+FFFD REPLACEMENT CHARACTER.
+    pub fn env_str_lossy(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.environment.get(key).map(|v| v.to_str_lossy())
+    }
+
     /// Returns the drv path of a [Derivation] struct.
     ///
     /// The drv path is calculated by invoking [build_text_path], using
-    /// the `name` with a `.drv` suffix as name, all [Derivation::input_sources] and
-    /// keys of [Derivation::input_derivations] as references, and the ATerm string of
-    /// the [Derivation] as content.
+    /// the `name` with a `.drv` suffix as name, [Derivation::reference_paths]
+    /// as references, and the ATerm string of the [Derivation] as content.
     pub fn calculate_derivation_path(
         &self,
         name: &str,
@@ -135,36 +188,85 @@ impl Derivation {
         // append .drv to the name
         let name = &format!("{name}.drv");
 
-        // collect the list of paths from input_sources and input_derivations
-        // into a (sorted, guaranteed by BTreeSet) list of references
-        let references: BTreeSet<String> = self
-            .input_sources
-            .iter()
-            .chain(self.input_derivations.keys())
-            .map(StorePath::to_absolute_path)
-            .collect();
-
-        build_text_path(name, self.to_aterm_bytes(), references)
+        build_text_path(name, self.to_aterm_bytes(), self.reference_paths())
             .map_err(|_e| DerivationError::InvalidOutputName(name.to_string()))
     }
 
+    /// Returns whether both derivations are equal, ignoring the calculated
+    /// output paths, both in [Derivation::outputs] and in the environment.
+    pub fn equals_modulo_outputs(&self, other: &Derivation) -> bool {
+        self.without_output_paths() == other.without_output_paths()
+    }
+
+    /// Returns a copy with all output paths trimmed, by setting
+    /// `outputs[$outputName].path` to [None] and `environment[$outputName]`
+    /// to the empty string.
+    fn without_output_paths(&self) -> Derivation {
+        let mut trimmed = self.clone();
+
+        for (output_name, output) in trimmed.outputs.iter_mut() {
+            trimmed.environment.insert(output_name.clone(), "".into());
+            output.path = None;
+        }
+
+        trimmed
+    }
+
+    /// Returns the command line to run the build with, i.e. the builder
+    /// followed by the arguments.
+    /// Fails if the builder is empty.
+    pub fn command_line(&self) -> Result<Vec<String>, DerivationError> {
+        if self.builder.is_empty() {
+            return Err(DerivationError::InvalidBuilder(self.builder.clone()));
+        }
+
+        Ok(std::iter::once(self.builder.clone())
+            .chain(self.arguments.iter().cloned())
+            .collect())
+    }
+
+    /// Returns the [CAHash] of the output with the given name, or [None]
+    /// if there's no such output or it isn't fixed.
+    pub fn output_ca_hash(&self, output_name: &str) -> Option<&CAHash> {
+        self.outputs.get(output_name)?.ca_hash.as_ref()
+    }
+
+    /// Returns whether this is a fixed-output derivation, i.e. it has a
+    /// single `out` output, which has a [CAHash].
+    pub fn is_fixed_output(&self) -> bool {
+        self.fixed_output_ca_hash().is_some()
+    }
+
+    /// Returns the [CAHash] of the `out` output, if this is a fixed-output
+    /// derivation.
+    fn fixed_output_ca_hash(&self) -> Option<&CAHash> {
+        if self.outputs.len() != 1 {
+            return None;
+        }
+
+        self.output_ca_hash("out")
+    }
+
     /// Returns the FOD digest, if the derivation is fixed-output, or None if
     /// it's not.
+    /// Fails if the fixed output uses a [CAHash] kind not valid in a
+    /// derivation context.
     /// TODO: this is kinda the string from [build_ca_path] with a
     /// [CAHash::Flat], what's fed to `build_store_path_from_fingerprint_parts`
     /// (except the out_output.path being an empty string)
-    pub fn fod_digest(&self) -> Option<[u8; 32]> {
-        if self.outputs.len() != 1 {
-            return None;
-        }
+    pub fn fod_digest(&self) -> Result<Option<[u8; 32]>, DerivationError> {
+        let Some(ca_hash) = self.fixed_output_ca_hash() else {
+            return Ok(None);
+        };
+        let out_output = &self.outputs["out"];
 
-        let out_output = self.outputs.get("out")?;
-        let ca_hash = &out_output.ca_hash.as_ref()?;
+        let ca_kind_prefix = ca_kind_prefix(ca_hash)
+            .map_err(|e| DerivationError::InvalidOutput("out".to_string(), e))?;
 
-        Some(
+        Ok(Some(
             Sha256::new_with_prefix(format!(
                 "fixed:out:{}{}:{}",
-                ca_kind_prefix(ca_hash),
+                ca_kind_prefix,
                 ca_hash.hash().to_nix_hex_string(),
                 out_output
                     .path
@@ -176,7 +278,7 @@ impl Derivation {
             ))
             .finalize()
             .into(),
-        )
+        ))
     }
 
     /// Calculates the hash of a derivation modulo fixed-output subderivations.
@@ -196,7 +298,13 @@ impl Derivation {
     /// input derivations, by their [StorePathRef].
     /// It will only be called in case the derivation is not a fixed-output
     /// derivation.
-    pub fn hash_derivation_modulo<F>(&self, fn_lookup_hash_derivation_modulo: F) -> [u8; 32]
+    ///
+    /// Fails if the derivation contains [CAHash] kinds that are not valid in
+    /// a derivation context. These are also rejected by [Derivation::validate].
+    pub fn hash_derivation_modulo<F>(
+        &self,
+        fn_lookup_hash_derivation_modulo: F,
+    ) -> Result<[u8; 32], DerivationError>
     where
         F: Fn(&StorePathRef) -> [u8; 32],
     {
@@ -206,7 +314,7 @@ impl Derivation {
         // call to this function.
         // We call [fn_lookup_hash_derivation_modulo] rather than recursing
         // ourselves, so callers can precompute this.
-        self.fod_digest().unwrap_or({
+        Ok(self.fod_digest()?.unwrap_or_else(|| {
             // For each input_derivation, look up the hash derivation modulo,
             // and replace the derivation path in the aterm with it's HEXLOWER digest.
             let aterm_bytes = self.to_aterm_bytes_with_replacements(&BTreeMap::from_iter(
@@ -221,7 +329,7 @@ impl Derivation {
 
             // write the ATerm of that to the hash function and return its digest.
             Sha256::new_with_prefix(aterm_bytes).finalize().into()
-        })
+        }))
     }
 
     /// This calculates all output paths of a Derivation and updates the struct.
@@ -297,12 +405,12 @@ fn output_path_name(derivation_name: &str, output_name: &str) -> String {
 
 /// For a [CAHash], return the "prefix" used for NAR purposes.
 /// For [CAHash::Flat], this is an empty string, for [CAHash::Nar], it's "r:".
-/// Panics for other [CAHash] kinds, as they're not valid in a derivation
-/// context.
-fn ca_kind_prefix(ca_hash: &CAHash) -> &'static str {
+/// Other [CAHash] kinds are not valid in a derivation context, and return an
+/// error.
+pub fn ca_kind_prefix(ca_hash: &CAHash) -> Result<&'static str, OutputError> {
     match ca_hash {
-        CAHash::Flat(_) => "",
-        CAHash::Nar(_) => "r:",
-        _ => panic!("invalid ca hash in derivation context: {ca_hash:?}"),
+        CAHash::Flat(_) => Ok(""),
+        CAHash::Nar(_) => Ok("r:"),
+        _ => Err(OutputError::InvalidCAHash(ca_hash.clone())),
     }
 }
@@ -57,6 +57,20 @@ pub(crate) fn parse(i: &[u8]) -> Result<Derivation, Error<&[u8]>> {
     }
 }
 
+/// Like [parse], but doesn't fail on trailing bytes after the derivation,
+/// returning them alongside it instead.
+pub(crate) fn parse_lenient(i: &[u8]) -> Result<(Derivation, &[u8]), Error<&[u8]>> {
+    match parse_derivation(i) {
+        Ok((rest, derivation)) => {
+            derivation.validate(true).map_err(Error::Validation)?;
+
+            Ok((derivation, rest))
+        }
+        Err(nom::Err::Incomplete(_)) => Err(Error::Incomplete),
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => Err(e.into()),
+    }
+}
+
 /// Consume a string containing the algo, and optionally a `r:`
 /// prefix, and a digest (bytes), return a [CAHash::Nar] or [CAHash::Flat].
 fn from_algo_and_mode_and_digest<B: AsRef<[u8]>>(
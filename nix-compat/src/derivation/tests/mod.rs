@@ -1,18 +1,24 @@
 use super::parse_error::ErrorKind;
-use crate::derivation::Derivation;
 use crate::derivation::output::Output;
 use crate::derivation::parse_error::NomError;
 use crate::derivation::parser::Error;
-use crate::store_path::StorePath;
+use crate::derivation::{Derivation, DerivationError};
+use crate::nixhash::CAHash;
+use crate::store_path::{self, BuildStorePathError, MAX_NAME_LEN, StorePath};
 use bstr::{BStr, BString};
 use hex_literal::hex;
 use rstest::rstest;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-const RESOURCES_PATHS: &str = "src/derivation/tests/derivation_tests";
+/// Fixture directory, resolved at compile time so tests don't depend on the
+/// working directory.
+const RESOURCES_PATHS: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/src/derivation/tests/derivation_tests"
+);
 
 #[rstest]
 fn check_serialization(
@@ -139,6 +145,29 @@ fn from_aterm_bytes_trailer() {
     Derivation::from_aterm_bytes(&buf).expect_err("must fail");
 }
 
+/// Read in a derivation in ATerm, but add some garbage at the end.
+/// Ensure the lenient parser still parses it, and captures the garbage.
+#[test]
+fn from_aterm_bytes_lenient_trailer() {
+    let buf: Vec<u8> = fs::read(format!(
+        "{}/ok/{}",
+        RESOURCES_PATHS, "0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv"
+    ))
+    .expect("unable to read .drv");
+    let expected = Derivation::from_aterm_bytes(&buf).expect("must succeed");
+
+    let mut corrupt = buf.clone();
+    corrupt.extend_from_slice(b"\x00garbage");
+
+    let (derivation, trailer) =
+        Derivation::from_aterm_bytes_lenient(&corrupt).expect("must succeed");
+    assert_eq!(expected, derivation);
+    assert_eq!(b"\x00garbage".as_slice(), trailer.as_slice());
+
+    let (_, trailer) = Derivation::from_aterm_bytes_lenient(&buf).expect("must succeed");
+    assert!(trailer.is_empty());
+}
+
 #[rstest]
 #[case::fixed_sha256("bar", "0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv")]
 #[case::simple_sha256("foo", "4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo.drv")]
@@ -162,30 +191,121 @@ fn derivation_path(#[case] name: &str, #[case] expected_path: &str) {
     );
 }
 
-/// This trims all output paths from a Derivation struct,
-/// by setting outputs[$outputName].path and environment[$outputName] to the empty string.
-fn derivation_without_output_paths(derivation: &Derivation) -> Derivation {
-    let mut trimmed_env = derivation.environment.clone();
-    let mut trimmed_outputs = derivation.outputs.clone();
-
-    for (output_name, output) in &derivation.outputs {
-        trimmed_env.insert(output_name.clone(), "".into());
-        assert!(trimmed_outputs.contains_key(output_name));
-        trimmed_outputs.insert(
-            output_name.to_string(),
+#[rstest]
+#[case::multiple_outputs("h32dahq0bx5rp1krcdx3a53asj21jvhk-has-multi-out.drv", &[])]
+#[case::input_derivation(
+    "4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo.drv",
+    &["/nix/store/0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv"]
+)]
+fn reference_paths(#[case] drv_path: &str, #[case] expected: &[&str]) {
+    let aterm_bytes =
+        fs::read(format!("{RESOURCES_PATHS}/ok/{drv_path}")).expect("unable to read .drv");
+    let derivation = Derivation::from_aterm_bytes(&aterm_bytes).expect("must succeed");
+
+    assert_eq!(
+        BTreeSet::from_iter(expected.iter().map(|p| p.to_string())),
+        derivation.reference_paths()
+    );
+}
+
+#[rstest]
+#[case::unicode(
+    "52a9id8hx688hvlnz4d1n25ml1jdykz0-unicode.drv",
+    "letters",
+    "räksmörgås\nrødgrød med fløde\nLübeck\n肥猪\nこんにちは / 今日は\n🌮\n".as_bytes(),
+    "räksmörgås\nrødgrød med fløde\nLübeck\n肥猪\nこんにちは / 今日は\n🌮\n"
+)]
+#[case::latin1(
+    "x6p0hg79i3wg0kkv7699935f7rrj9jf3-latin1.drv",
+    "chars",
+    b"\xc5\xc4\xd6",
+    "\u{FFFD}\u{FFFD}\u{FFFD}"
+)]
+fn env_accessors(
+    #[case] drv_path: &str,
+    #[case] key: &str,
+    #[case] expected_bytes: &[u8],
+    #[case] expected_lossy: &str,
+) {
+    let aterm_bytes =
+        fs::read(format!("{RESOURCES_PATHS}/ok/{drv_path}")).expect("unable to read .drv");
+    let derivation = Derivation::from_aterm_bytes(&aterm_bytes).expect("must succeed");
+
+    assert_eq!(Some(expected_bytes), derivation.env_bytes(key));
+    assert_eq!(
+        Some(expected_lossy),
+        derivation.env_str_lossy(key).as_deref()
+    );
+
+    assert_eq!(None, derivation.env_bytes("missing"));
+    assert_eq!(None, derivation.env_str_lossy("missing"));
+}
+
+#[test]
+fn to_pretty_aterm() {
+    let aterm_bytes = fs::read(format!(
+        "{RESOURCES_PATHS}/ok/4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo.drv"
+    ))
+    .expect("unable to read .drv");
+    let derivation = Derivation::from_aterm_bytes(&aterm_bytes).expect("must succeed");
+
+    let pretty = derivation.to_pretty_aterm();
+    assert_eq!(
+        r#"Derive(
+  outputs: [
+    ("out", "/nix/store/5vyvcwah9l9kf07d52rcgdk70g2f4y13-foo", "", ""),
+  ],
+  inputDrvs: [
+    ("/nix/store/0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv", ["out"]),
+  ],
+  inputSrcs: [],
+  system: ":",
+  builder: ":",
+  args: [],
+  env: [
+    ("bar", "/nix/store/4q0pg5zpfmznxscq3avycvf9xdvx50n3-bar"),
+    ("builder", ":"),
+    ("name", "foo"),
+    ("out", "/nix/store/5vyvcwah9l9kf07d52rcgdk70g2f4y13-foo"),
+    ("system", ":"),
+  ],
+)"#,
+        pretty
+    );
+
+    // This is for display only, and not a valid ATerm.
+    Derivation::from_aterm_bytes(pretty.as_bytes()).expect_err("must fail");
+}
+
+/// [CAHash::Text] outputs are not valid in a derivation context, but
+/// serializing them must not panic.
+#[test]
+fn to_aterm_text_ca_hash() {
+    let drv = Derivation {
+        outputs: BTreeMap::from([(
+            "out".to_string(),
             Output {
                 path: None,
-                ..output.clone()
+                ca_hash: Some(CAHash::Text([0; 32])),
             },
-        );
-    }
+        )]),
+        ..Default::default()
+    };
 
-    // replace environment and outputs with the trimmed variants
-    Derivation {
-        environment: trimmed_env,
-        outputs: trimmed_outputs,
-        ..derivation.clone()
-    }
+    let pretty = drv.to_pretty_aterm();
+    assert!(
+        pretty.contains(&format!(
+            r#"("out", "", "text:sha256", "{}")"#,
+            "0".repeat(64)
+        )),
+        "{pretty}"
+    );
+
+    let aterm = String::from_utf8(drv.to_aterm_bytes()).unwrap();
+    assert!(
+        aterm.starts_with(r#"Derive([("out","","text:sha256","#),
+        "{aterm}"
+    );
 }
 
 #[rstest]
@@ -197,10 +317,29 @@ fn hash_derivation_modulo_fixed(#[case] drv_path: &str, #[case] expected_digest:
         fs::read(format!("{RESOURCES_PATHS}/ok/{drv_path}.json")).expect("unable to read JSON");
     let drv: Derivation = serde_json::from_slice(&json_bytes).expect("must deserialize");
 
-    let actual = drv.hash_derivation_modulo(|_| panic!("must not be called"));
+    let actual = drv
+        .hash_derivation_modulo(|_| panic!("must not be called"))
+        .expect("must succeed");
     assert_eq!(expected_digest, actual);
 }
 
+#[rstest]
+#[case::fixed_sha256("0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv", true)]
+#[case::simple_sha256("4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo.drv", false)]
+fn output_ca_hash(#[case] drv_path: &str, #[case] fixed: bool) {
+    let json_bytes =
+        fs::read(format!("{RESOURCES_PATHS}/ok/{drv_path}.json")).expect("unable to read JSON");
+    let drv: Derivation = serde_json::from_slice(&json_bytes).expect("must deserialize");
+
+    assert_eq!(fixed, drv.is_fixed_output());
+    assert_eq!(
+        drv.outputs["out"].ca_hash.as_ref(),
+        drv.output_ca_hash("out")
+    );
+    assert_eq!(fixed, drv.output_ca_hash("out").is_some());
+    assert_eq!(None, drv.output_ca_hash("missing"));
+}
+
 /// This reads a Derivation (in A-Term), trims out all fields containing
 /// calculated output paths, then triggers the output path calculation and
 /// compares the struct to match what was originally read in.
@@ -226,43 +365,48 @@ fn output_paths(#[case] name: &str, #[case] drv_path_str: &str) {
 
     // create a version without output paths, simulating we constructed the
     // struct.
-    let mut derivation = derivation_without_output_paths(&expected_derivation);
+    let mut derivation = expected_derivation.without_output_paths();
 
     // calculate the hash_derivation_modulo of Derivation
     // We don't expect the lookup function to be called for most derivations.
-    let actual_hash_derivation_modulo = derivation.hash_derivation_modulo(|parent_drv_path| {
-        // 4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo.drv may lookup /nix/store/0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv
-        // ch49594n9avinrf8ip0aslidkc4lxkqv-foo.drv may lookup /nix/store/ss2p4wmxijn652haqyd7dckxwl4c7hxx-bar.drv
-        if name == "foo"
-            && ((drv_path_str == "4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo.drv"
-                && parent_drv_path.to_string() == "0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv")
-                || (drv_path_str == "ch49594n9avinrf8ip0aslidkc4lxkqv-foo.drv"
-                    && parent_drv_path.to_string() == "ss2p4wmxijn652haqyd7dckxwl4c7hxx-bar.drv"))
-        {
-            // do the lookup, by reading in the fixture of the requested
-            // drv_name, and calculating its drv replacement (on the non-stripped version)
-            // In a real-world scenario you would have already done this during construction.
-
-            let json_bytes = fs::read(format!(
-                "{}/ok/{}.json",
-                RESOURCES_PATHS,
-                Path::new(&parent_drv_path.to_string())
-                    .file_name()
-                    .unwrap()
-                    .to_string_lossy()
-            ))
-            .expect("unable to read JSON");
-
-            let drv: Derivation = serde_json::from_slice(&json_bytes).expect("must deserialize");
-
-            // calculate hash_derivation_modulo for each parent.
-            // This may not trigger subsequent requests, as both parents are FOD.
-            drv.hash_derivation_modulo(|_| panic!("must not lookup"))
-        } else {
-            // we only expect this to be called in the "foo" testcase, for the "bar derivations"
-            panic!("may only be called for foo testcase on bar derivations");
-        }
-    });
+    let actual_hash_derivation_modulo = derivation
+        .hash_derivation_modulo(|parent_drv_path| {
+            // 4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo.drv may lookup /nix/store/0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv
+            // ch49594n9avinrf8ip0aslidkc4lxkqv-foo.drv may lookup /nix/store/ss2p4wmxijn652haqyd7dckxwl4c7hxx-bar.drv
+            if name == "foo"
+                && ((drv_path_str == "4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo.drv"
+                    && parent_drv_path.to_string() == "0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv")
+                    || (drv_path_str == "ch49594n9avinrf8ip0aslidkc4lxkqv-foo.drv"
+                        && parent_drv_path.to_string()
+                            == "ss2p4wmxijn652haqyd7dckxwl4c7hxx-bar.drv"))
+            {
+                // do the lookup, by reading in the fixture of the requested
+                // drv_name, and calculating its drv replacement (on the non-stripped version)
+                // In a real-world scenario you would have already done this during construction.
+
+                let json_bytes = fs::read(format!(
+                    "{}/ok/{}.json",
+                    RESOURCES_PATHS,
+                    Path::new(&parent_drv_path.to_string())
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                ))
+                .expect("unable to read JSON");
+
+                let drv: Derivation =
+                    serde_json::from_slice(&json_bytes).expect("must deserialize");
+
+                // calculate hash_derivation_modulo for each parent.
+                // This may not trigger subsequent requests, as both parents are FOD.
+                drv.hash_derivation_modulo(|_| panic!("must not lookup"))
+                    .expect("must succeed")
+            } else {
+                // we only expect this to be called in the "foo" testcase, for the "bar derivations"
+                panic!("may only be called for foo testcase on bar derivations");
+            }
+        })
+        .expect("must succeed");
 
     derivation
         .calculate_output_paths(name, &actual_hash_derivation_modulo)
@@ -272,6 +416,93 @@ fn output_paths(#[case] name: &str, #[case] drv_path_str: &str) {
     assert_eq!(expected_derivation, derivation);
 }
 
+#[test]
+fn equals_modulo_outputs() {
+    let aterm_bytes = fs::read(format!(
+        "{RESOURCES_PATHS}/ok/4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo.drv"
+    ))
+    .expect("unable to read .drv");
+    let derivation = Derivation::from_aterm_bytes(&aterm_bytes).expect("must succeed");
+
+    let mut other = derivation.clone();
+    let output_path =
+        StorePath::from_bytes(b"00bgd045z0d4icpbc2yyz4gx48ak44la-foo").expect("must parse");
+    other
+        .environment
+        .insert("out".to_string(), output_path.to_absolute_path().into());
+    other.outputs.get_mut("out").unwrap().path = Some(output_path);
+    assert_ne!(derivation, other);
+    assert!(derivation.equals_modulo_outputs(&other));
+
+    other.builder = "/bin/sh".to_string();
+    assert!(!derivation.equals_modulo_outputs(&other));
+}
+
+#[test]
+fn command_line() {
+    let aterm_bytes = fs::read(format!(
+        "{RESOURCES_PATHS}/ok/4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo.drv"
+    ))
+    .expect("unable to read .drv");
+    let mut derivation = Derivation::from_aterm_bytes(&aterm_bytes).expect("must succeed");
+
+    assert_eq!(
+        vec![":".to_string()],
+        derivation.command_line().expect("must succeed")
+    );
+
+    derivation.arguments = vec!["-e".to_string(), "builder.sh".to_string()];
+    assert_eq!(
+        vec![":", "-e", "builder.sh"],
+        derivation.command_line().expect("must succeed")
+    );
+}
+
+#[test]
+fn command_line_empty_builder() {
+    let derivation = Derivation {
+        arguments: vec!["-c".to_string(), "true".to_string()],
+        ..Default::default()
+    };
+
+    assert!(matches!(
+        derivation.command_line(),
+        Err(DerivationError::InvalidBuilder(_))
+    ));
+}
+
+/// Output path names longer than allowed by Nix are rejected during the output
+/// path calculation, taking the output name suffix into account.
+#[test]
+fn output_paths_name_too_long() {
+    let mut derivation = Derivation::default();
+    derivation
+        .outputs
+        .insert("out".to_string(), Output::default());
+    derivation
+        .outputs
+        .insert("dev".to_string(), Output::default());
+
+    // `-dev` is appended to the name of the dev output.
+    let name = "a".repeat(MAX_NAME_LEN - 4);
+    derivation
+        .clone()
+        .calculate_output_paths(&name, &[0; 32])
+        .expect("must succeed");
+
+    let name = "a".repeat(MAX_NAME_LEN - 3);
+    match derivation.calculate_output_paths(&name, &[0; 32]) {
+        Err(DerivationError::InvalidOutputDerivationPath(
+            output_name,
+            BuildStorePathError::InvalidStorePath(store_path::Error::NameTooLong(len)),
+        )) => {
+            assert_eq!("dev", output_name);
+            assert_eq!(MAX_NAME_LEN + 1, len);
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
 /// Exercises the output path calculation functions like a constructing client
 /// (an implementation of builtins.derivation) would do:
 ///
@@ -343,7 +574,9 @@ fn output_path_construction() {
     // calculate bar output paths
     let bar_calc_result = bar_drv.calculate_output_paths(
         "bar",
-        &bar_drv.hash_derivation_modulo(|_| panic!("is FOD, should not lookup")),
+        &bar_drv
+            .hash_derivation_modulo(|_| panic!("is FOD, should not lookup"))
+            .expect("must succeed"),
     );
     assert!(bar_calc_result.is_ok());
 
@@ -360,8 +593,9 @@ fn output_path_construction() {
     // now construct foo, which requires bar_drv
     // Note how we refer to the output path, drv name and replacement_str (with calculated output paths) of bar.
     let bar_output_path = &bar_drv.outputs.get("out").expect("must exist").path;
-    let bar_drv_hash_derivation_modulo =
-        bar_drv.hash_derivation_modulo(|_| panic!("is FOD, should not lookup"));
+    let bar_drv_hash_derivation_modulo = bar_drv
+        .hash_derivation_modulo(|_| panic!("is FOD, should not lookup"))
+        .expect("must succeed");
 
     let bar_drv_path = bar_drv
         .calculate_derivation_path("bar")
@@ -408,12 +642,14 @@ fn output_path_construction() {
     // calculate foo output paths
     let foo_calc_result = foo_drv.calculate_output_paths(
         "foo",
-        &foo_drv.hash_derivation_modulo(|drv_path| {
-            if drv_path.to_string() != "0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv" {
-                panic!("lookup called with unexpected drv_path: {drv_path}");
-            }
-            bar_drv_hash_derivation_modulo
-        }),
+        &foo_drv
+            .hash_derivation_modulo(|drv_path| {
+                if drv_path.to_string() != "0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv" {
+                    panic!("lookup called with unexpected drv_path: {drv_path}");
+                }
+                bar_drv_hash_derivation_modulo
+            })
+            .expect("must succeed"),
     );
     assert!(foo_calc_result.is_ok());
 
@@ -113,7 +113,9 @@ impl Derivation {
 mod test {
     use std::collections::BTreeMap;
 
-    use crate::derivation::{CAHash, Derivation, Output};
+    use crate::derivation::{
+        CAHash, Derivation, DerivationError, Output, OutputError, ca_kind_prefix,
+    };
 
     /// Regression test: produce a Derivation that's almost valid, except its
     /// fixed-output output has the wrong hash specified.
@@ -138,4 +140,39 @@ mod test {
 
         drv.validate(false).expect_err("must fail");
     }
+
+    /// [CAHash::Text] is not valid in a derivation context, make sure the
+    /// FOD digest and hash derivation modulo calculations fail cleanly instead
+    /// of panicking.
+    #[test]
+    fn fod_digest_invalid_ca_hash() {
+        let ca_hash = CAHash::Text([0; 32]);
+        assert_eq!(
+            Err(OutputError::InvalidCAHash(ca_hash.clone())),
+            ca_kind_prefix(&ca_hash)
+        );
+
+        let drv = Derivation {
+            outputs: BTreeMap::from([(
+                "out".to_string(),
+                Output {
+                    path: None,
+                    ca_hash: Some(ca_hash.clone()),
+                },
+            )]),
+            ..Default::default()
+        };
+
+        let expected_err = || {
+            DerivationError::InvalidOutput(
+                "out".to_string(),
+                OutputError::InvalidCAHash(ca_hash.clone()),
+            )
+        };
+        assert_eq!(Err(expected_err()), drv.fod_digest());
+        assert_eq!(
+            Err(expected_err()),
+            drv.hash_derivation_modulo(|_| panic!("must not lookup"))
+        );
+    }
 }
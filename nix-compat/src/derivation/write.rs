@@ -4,15 +4,15 @@
 //! [ATerm]: http://program-transformation.org/Tools/ATermFormat.html
 
 use crate::aterm::escape_bytes;
-use crate::derivation::{ca_kind_prefix, output::Output};
-use crate::nixbase32;
+use crate::derivation::{Derivation, output::Output};
+use crate::nixhash::CAHash;
 use crate::store_path::{STORE_DIR_WITH_SLASH, StorePath};
 use bstr::BString;
 use data_encoding::HEXLOWER;
 
 use std::{
     collections::{BTreeMap, BTreeSet},
-    io,
+    fmt, io,
     io::Error,
     io::Write,
 };
@@ -41,7 +41,7 @@ where
     fn aterm_write(&self, writer: &mut impl Write) -> std::io::Result<()> {
         write_char(writer, QUOTE)?;
         writer.write_all(STORE_DIR_WITH_SLASH.as_bytes())?;
-        writer.write_all(nixbase32::encode(self.digest()).as_bytes())?;
+        writer.write_all(self.digest_str().as_bytes())?;
         write_char(writer, '-')?;
         writer.write_all(self.name().as_ref().as_bytes())?;
         write_char(writer, QUOTE)?;
@@ -120,13 +120,7 @@ pub(crate) fn write_outputs(
         let path_str = output.path_str();
         let mut elements: Vec<&str> = vec![output_name, &path_str];
 
-        let (mode_and_algo, digest) = match &output.ca_hash {
-            Some(ca_hash) => (
-                format!("{}{}", ca_kind_prefix(ca_hash), ca_hash.hash().algo()),
-                data_encoding::HEXLOWER.encode(ca_hash.hash().digest_as_bytes()),
-            ),
-            None => ("".to_string(), "".to_string()),
-        };
+        let (mode_and_algo, digest) = output_hash_fields(output);
 
         elements.push(&mode_and_algo);
         elements.push(&digest);
@@ -140,6 +134,30 @@ pub(crate) fn write_outputs(
     Ok(())
 }
 
+/// Returns the hash algo (prefixed with the mode) and hex digest fields of an
+/// [Output], which are empty strings for non-fixed outputs.
+///
+/// Unlike [ca_kind_prefix](crate::derivation::ca_kind_prefix), this also
+/// renders [CAHash::Text] outputs (as `text:sha256`, like Nix does), so
+/// serializing an unvalidated [Derivation] doesn't fail.
+fn output_hash_fields(output: &Output) -> (String, String) {
+    match &output.ca_hash {
+        Some(ca_hash) => {
+            let mode_prefix = match ca_hash {
+                CAHash::Flat(_) => "",
+                CAHash::Nar(_) => "r:",
+                CAHash::Text(_) => "text:",
+            };
+
+            (
+                format!("{}{}", mode_prefix, ca_hash.hash().algo()),
+                data_encoding::HEXLOWER.encode(ca_hash.hash().digest_as_bytes()),
+            )
+        }
+        None => ("".to_string(), "".to_string()),
+    }
+}
+
 pub(crate) fn write_input_derivations(
     writer: &mut impl Write,
     input_derivations: &BTreeMap<impl AtermWriteable, BTreeSet<String>>,
@@ -244,3 +262,89 @@ where
 
     Ok(())
 }
+
+/// Quotes and escapes a field like in the ATerm serialization, replacing
+/// invalid UTF-8 for display purposes.
+fn pretty_field<S: AsRef<[u8]>>(s: S) -> String {
+    format!("\"{}\"", String::from_utf8_lossy(&escape_bytes(s)))
+}
+
+/// Writes a labelled list, with one element per line.
+fn write_pretty_list<I>(w: &mut impl fmt::Write, label: &str, elements: I) -> fmt::Result
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut elements = elements.into_iter().peekable();
+    if elements.peek().is_none() {
+        return writeln!(w, "  {label}: [],");
+    }
+
+    writeln!(w, "  {label}: [")?;
+    for element in elements {
+        writeln!(w, "    {element},")?;
+    }
+    writeln!(w, "  ],")
+}
+
+/// Writes the structure of the ATerm serialization of a [Derivation] with
+/// labelled fields, newlines and indentation. See
+/// [Derivation::to_pretty_aterm].
+pub(crate) fn write_pretty(w: &mut impl fmt::Write, drv: &Derivation) -> fmt::Result {
+    writeln!(w, "{DERIVATION_PREFIX}{PAREN_OPEN}")?;
+
+    write_pretty_list(
+        w,
+        "outputs",
+        drv.outputs.iter().map(|(output_name, output)| {
+            let (mode_and_algo, digest) = output_hash_fields(output);
+            format!(
+                "({}, {}, {}, {})",
+                pretty_field(output_name),
+                pretty_field(output.path_str().as_ref()),
+                pretty_field(mode_and_algo),
+                pretty_field(digest)
+            )
+        }),
+    )?;
+
+    write_pretty_list(
+        w,
+        "inputDrvs",
+        drv.input_derivations
+            .iter()
+            .map(|(drv_path, output_names)| {
+                format!(
+                    "({}, [{}])",
+                    pretty_field(drv_path.to_absolute_path()),
+                    output_names
+                        .iter()
+                        .map(pretty_field)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }),
+    )?;
+
+    write_pretty_list(
+        w,
+        "inputSrcs",
+        drv.input_sources
+            .iter()
+            .map(|path| pretty_field(path.to_absolute_path())),
+    )?;
+
+    writeln!(w, "  system: {},", pretty_field(&drv.system))?;
+    writeln!(w, "  builder: {},", pretty_field(&drv.builder))?;
+
+    write_pretty_list(w, "args", drv.arguments.iter().map(pretty_field))?;
+
+    write_pretty_list(
+        w,
+        "env",
+        drv.environment
+            .iter()
+            .map(|(k, v)| format!("({}, {})", pretty_field(k), pretty_field(v))),
+    )?;
+
+    write!(w, "{PAREN_CLOSE}")
+}
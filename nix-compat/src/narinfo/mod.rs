@@ -20,11 +20,17 @@
 use bitflags::bitflags;
 use data_encoding::HEXLOWER;
 use std::{
+    collections::BTreeSet,
     fmt::{self, Display},
-    mem,
+    io, mem,
 };
 
-use crate::{nixbase32, nixhash::CAHash, store_path::StorePathRef};
+use crate::{
+    derivation::Derivation,
+    nixbase32,
+    nixhash::{CAHash, NixHash},
+    store_path::{StorePath, StorePathRef, build_ca_path},
+};
 
 mod fingerprint;
 mod signature;
@@ -93,6 +99,12 @@ bitflags! {
 
         /// entirely missing References field, produced by harmonia
         const REFERENCES_MISSING = 1 << 5;
+
+        /// Deriver: /nix/store/…-foo.drv, an absolute path instead of
+        /// just the store path basename
+        ///
+        /// Only relevant if [NarInfo::deriver] is [Some].
+        const DERIVER_ABSOLUTE = 1 << 6;
     }
 }
 
@@ -253,6 +265,14 @@ impl<'a> NarInfo<'a> {
                     }
                 }
                 TAG_DERIVER => {
+                    let val = match val.strip_prefix("/nix/store/") {
+                        Some(val) => {
+                            flags |= Flags::DERIVER_ABSOLUTE;
+                            val
+                        }
+                        None => val,
+                    };
+
                     match val.strip_suffix(".drv") {
                         Some(val) => {
                             let val = StorePathRef::from_bytes(val.as_bytes())
@@ -321,6 +341,101 @@ impl<'a> NarInfo<'a> {
         })
     }
 
+    /// Creates the skeleton of a [NarInfo] for the output with the given name
+    /// of a derivation, whose NAR has the given SHA-256 digest and size.
+    ///
+    /// The store path, content address and system are taken from the
+    /// derivation, the deriver is the passed derivation path. The references
+    /// are the inputs of the derivation, i.e. its input sources and the used
+    /// outputs of its input derivations, whose store paths are looked up with
+    /// the passed function. These are only the paths the output may refer to,
+    /// so they should usually be narrowed down to the ones found in the NAR.
+    /// Outputs of fixed-output derivations have no references.
+    ///
+    /// The cache-specific fields are left empty, [NarInfo::url] has to be set
+    /// before publishing the NAR info.
+    pub fn from_derivation_output<F>(
+        drv: &'a Derivation,
+        drv_path: &StorePathRef<'a>,
+        output_name: &str,
+        nar_hash: [u8; 32],
+        nar_size: u64,
+        mut fn_lookup_output_path: F,
+    ) -> Result<Self, Error>
+    where
+        F: FnMut(StorePathRef<'a>, &str) -> Option<StorePathRef<'a>>,
+    {
+        let output = drv
+            .outputs
+            .get(output_name)
+            .ok_or_else(|| Error::MissingOutput(output_name.to_string()))?;
+        let store_path = output
+            .path
+            .as_ref()
+            .ok_or_else(|| Error::MissingOutputPath(output_name.to_string()))?
+            .as_ref();
+
+        let drv_name: &'a str = drv_path.name();
+        let deriver_name = drv_name
+            .strip_suffix(".drv")
+            .ok_or(Error::InvalidDeriverStorePathMissingSuffix)?;
+        let deriver = StorePathRef::from_name_and_digest_fixed(deriver_name, *drv_path.digest())
+            .map_err(Error::InvalidDeriverStorePath)?;
+
+        let references = match &output.ca_hash {
+            Some(ca_hash) => {
+                let expected: Option<StorePath<String>> =
+                    build_ca_path(store_path.name(), ca_hash, Vec::<&str>::new(), false).ok();
+                if expected.is_none_or(|expected| expected.as_ref() != store_path) {
+                    return Err(Error::StorePathCAMismatch(store_path.to_absolute_path()));
+                }
+
+                vec![]
+            }
+            None => {
+                let mut references: BTreeSet<StorePathRef<'a>> =
+                    drv.input_sources.iter().map(StorePath::as_ref).collect();
+
+                for (input_drv_path, input_outputs) in &drv.input_derivations {
+                    for input_output in input_outputs {
+                        let path = fn_lookup_output_path(input_drv_path.as_ref(), input_output)
+                            .ok_or_else(|| {
+                                Error::UnresolvedInputOutput(
+                                    input_drv_path.to_absolute_path(),
+                                    input_output.clone(),
+                                )
+                            })?;
+                        references.insert(path);
+                    }
+                }
+
+                references.into_iter().collect()
+            }
+        };
+
+        let narinfo = NarInfo {
+            flags: Flags::empty(),
+            store_path,
+            nar_hash,
+            nar_size,
+            references,
+            signatures: vec![],
+            ca: output.ca_hash.clone(),
+            system: Some(&drv.system),
+            deriver: Some(deriver),
+            url: "",
+            compression: None,
+            file_hash: None,
+            file_size: None,
+        };
+
+        if narinfo.ca_matches_nar() == Some(false) {
+            return Err(Error::NarHashCAMismatch);
+        }
+
+        Ok(narinfo)
+    }
+
     /// Computes the fingerprint string for certain fields in this [NarInfo].
     /// This fingerprint is signed in [self.signatures].
     pub fn fingerprint(&self) -> String {
@@ -332,6 +447,51 @@ impl<'a> NarInfo<'a> {
         )
     }
 
+    /// Checks whether [NarInfo::ca] is consistent with [NarInfo::nar_hash].
+    ///
+    /// For paths content-addressed by a sha256 NAR hash, the CA digest has to
+    /// be the NAR hash. Returns [None] for all other (or missing) content
+    /// addresses, where the two can't be compared.
+    pub fn ca_matches_nar(&self) -> Option<bool> {
+        match &self.ca {
+            Some(CAHash::Nar(hash @ NixHash::Sha256(_))) => {
+                Some(hash.ct_eq(&NixHash::Sha256(self.nar_hash)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks whether all [NarInfo::references] exist, as reported by the
+    /// passed resolver. Returns the references reported as missing otherwise.
+    pub fn check_references<F>(&self, mut resolver: F) -> Result<(), Vec<StorePathRef<'a>>>
+    where
+        F: FnMut(&StorePathRef<'a>) -> bool,
+    {
+        let missing: Vec<_> = self
+            .references
+            .iter()
+            .filter(|reference| !resolver(reference))
+            .cloned()
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(missing)
+        }
+    }
+
+    /// Returns the signatures made by the key with the given name,
+    /// e.g. `cache.nixos.org-1`.
+    pub fn signatures_by<'s>(
+        &'s self,
+        name: &'s str,
+    ) -> impl Iterator<Item = &'s SignatureRef<'a>> + 's {
+        self.signatures
+            .iter()
+            .filter(move |signature| *signature.name() == name)
+    }
+
     /// Adds a signature, using the passed signer to sign.
     /// This is generic over algo implementations / providers,
     /// so users can bring their own signers.
@@ -346,10 +506,24 @@ impl<'a> NarInfo<'a> {
 
         self.signatures.push(sig);
     }
-}
 
-impl Display for NarInfo<'_> {
-    fn fmt(&self, w: &mut fmt::Formatter) -> fmt::Result {
+    /// Writes the NAR info file to the given writer, like its [Display]
+    /// implementation, but without an intermediate [String].
+    pub fn write_to(&self, w: &mut impl io::Write) -> io::Result<()> {
+        let mut adapter = IoAdapter {
+            inner: w,
+            error: None,
+        };
+
+        self.write_fields(&mut adapter).map_err(|_| {
+            adapter
+                .error
+                .take()
+                .unwrap_or_else(|| io::Error::other("formatter error"))
+        })
+    }
+
+    fn write_fields(&self, w: &mut impl fmt::Write) -> fmt::Result {
         writeln!(w, "StorePath: /nix/store/{}", self.store_path)?;
         writeln!(w, "URL: {}", self.url)?;
 
@@ -382,7 +556,11 @@ impl Display for NarInfo<'_> {
         }
 
         if let Some(deriver) = &self.deriver {
-            writeln!(w, "Deriver: {deriver}.drv")?;
+            if self.flags.contains(Flags::DERIVER_ABSOLUTE) {
+                writeln!(w, "Deriver: /nix/store/{deriver}.drv")?;
+            } else {
+                writeln!(w, "Deriver: {deriver}.drv")?;
+            }
         } else if self.flags.contains(Flags::EXPLICIT_UNKNOWN_DERIVER) {
             writeln!(w, "Deriver: unknown-deriver")?;
         }
@@ -403,6 +581,28 @@ impl Display for NarInfo<'_> {
     }
 }
 
+impl Display for NarInfo<'_> {
+    fn fmt(&self, w: &mut fmt::Formatter) -> fmt::Result {
+        self.write_fields(w)
+    }
+}
+
+/// [fmt::Write] adapter writing to an [io::Write], keeping the [io::Error]
+/// that [fmt::Error] can't carry.
+struct IoAdapter<'w, W> {
+    inner: &'w mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> fmt::Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("duplicate field: {0}")]
@@ -443,6 +643,21 @@ pub enum Error {
 
     #[error("unable to parse CA field: {0}")]
     UnableToParseCA(String),
+
+    #[error("derivation has no output {0}")]
+    MissingOutput(String),
+
+    #[error("derivation output {0} has no store path")]
+    MissingOutputPath(String),
+
+    #[error("unable to look up output {1} of input derivation {0}")]
+    UnresolvedInputOutput(String, String),
+
+    #[error("store path {0} does not match its content address")]
+    StorePathCAMismatch(String),
+
+    #[error("content address does not match NAR hash")]
+    NarHashCAMismatch,
 }
 
 #[cfg(test)]
@@ -455,15 +670,17 @@ const DUMMY_VERIFYING_KEY: &str =
 mod test {
     use hex_literal::hex;
     use pretty_assertions::assert_eq;
+    use rstest::rstest;
     use std::sync::LazyLock;
     use std::{io, str};
 
     use crate::{
+        derivation::Derivation,
         nixhash::{CAHash, NixHash},
         store_path::StorePathRef,
     };
 
-    use super::{Flags, NarInfo};
+    use super::{Error, Flags, NarInfo};
 
     static CASES: LazyLock<&'static [&'static str]> = LazyLock::new(|| {
         let data = zstd::decode_all(io::Cursor::new(include_bytes!(
@@ -487,6 +704,16 @@ mod test {
         }
     }
 
+    #[test]
+    fn write_to() {
+        for &input in *CASES {
+            let parsed = NarInfo::parse(input).expect("should parse");
+            let mut output = Vec::new();
+            parsed.write_to(&mut output).expect("should write");
+            assert_eq!(parsed.to_string().as_bytes(), output.as_slice());
+        }
+    }
+
     #[test]
     fn references_out_of_order() {
         let parsed = NarInfo::parse(
@@ -542,6 +769,47 @@ CA: fixed:r:sha1:1ak1ymbmsfx7z8kh09jzkr3a4dvkrfjw
         );
     }
 
+    #[rstest]
+    #[case::matching(
+        "fixed:r:sha256:0h1bm4sj1cnfkxgyhvgi8df1qavnnv94sd0v09wcrm971602shfg",
+        Some(true)
+    )]
+    #[case::mismatching(
+        "fixed:r:sha256:1wsgz5cbawm6ifh59ccixw4n717wng5n6wp4sxyav0ad78lzvbb0",
+        Some(false)
+    )]
+    #[case::nar_sha1("fixed:r:sha1:1ak1ymbmsfx7z8kh09jzkr3a4dvkrfjw", None)]
+    #[case::flat_sha256(
+        "fixed:sha256:0h1bm4sj1cnfkxgyhvgi8df1qavnnv94sd0v09wcrm971602shfg",
+        None
+    )]
+    fn ca_matches_nar(#[case] ca: &str, #[case] expected: Option<bool>) {
+        // This doesn't exist as such in cache.nixos.org.
+        // We explicitly changed the CA field for the sake of this test.
+        let input = format!(
+            r#"StorePath: /nix/store/k20pahypzvr49fy82cw5sx72hdfg3qcr-texlive-hyphenex-37354
+URL: nar/0i5biw0g01514llhfswxy6xfav8lxxdq1xg6ik7hgsqbpw0f06yi.nar.xz
+Compression: xz
+FileHash: sha256:0i5biw0g01514llhfswxy6xfav8lxxdq1xg6ik7hgsqbpw0f06yi
+FileSize: 7120
+NarHash: sha256:0h1bm4sj1cnfkxgyhvgi8df1qavnnv94sd0v09wcrm971602shfg
+NarSize: 22552
+References: 
+CA: {ca}
+"#
+        );
+        let parsed = NarInfo::parse(&input).expect("should parse");
+
+        assert_eq!(expected, parsed.ca_matches_nar());
+    }
+
+    #[test]
+    fn missing_ca_matches_nar() {
+        let parsed = NarInfo::parse(CASES[0]).expect("should parse");
+        assert!(parsed.ca.is_none());
+        assert_eq!(None, parsed.ca_matches_nar());
+    }
+
     #[test]
     fn compression_default() {
         // This doesn't exist as such in cache.nixos.org.
@@ -587,11 +855,9 @@ Sig: cache.nixos.org-1:92fl0i5q7EyegCj5Yf4L0bENkWuVAtgveiRcTEEUH0P6HvCE1xFcPbz/0
         assert_eq!(parsed.to_string(), input);
     }
 
-    #[test]
-    fn explicit_unknown_deriver() {
-        // This is a NARInfo "produced by a legacy tool" according to Nix commit
-        // c60715e937e3773bbb8a114fc9b9c6577f8c5cb5
-        let input = r#"StorePath: /nix/store/00bgd045z0d4icpbc2yyz4gx48ak44la-net-tools-1.60_p20170221182432
+    // This is a NARInfo "produced by a legacy tool" according to Nix commit
+    // c60715e937e3773bbb8a114fc9b9c6577f8c5cb5
+    const EXPLICIT_UNKNOWN_DERIVER: &str = r#"StorePath: /nix/store/00bgd045z0d4icpbc2yyz4gx48ak44la-net-tools-1.60_p20170221182432
 URL: nar/1094wph9z4nwlgvsd53abfz8i117ykiv5dwnq9nnhz846s7xqd7d.nar.xz
 Compression: xz
 FileHash: sha256:1094wph9z4nwlgvsd53abfz8i117ykiv5dwnq9nnhz846s7xqd7d
@@ -603,10 +869,50 @@ Deriver: unknown-deriver
 Sig: cache.nixos.org-1:sn5s/RrqEI+YG6/PjwdbPjcAC7rcta7sJU4mFOawGvJBLsWkyLtBrT2EuFt/LJjWkTZ+ZWOI9NTtjo/woMdvAg==
 Sig: hydra.other.net-1:JXQ3Z/PXf0EZSFkFioa4FbyYpbbTbHlFBtZf4VqU0tuMTWzhMD7p9Q7acJjLn3jofOtilAAwRILKIfVuyrbjAA==
 "#;
-        let parsed = NarInfo::parse(input).expect("should parse");
+
+    #[test]
+    fn explicit_unknown_deriver() {
+        let parsed = NarInfo::parse(EXPLICIT_UNKNOWN_DERIVER).expect("should parse");
 
         assert!(parsed.flags.contains(Flags::EXPLICIT_UNKNOWN_DERIVER));
         assert!(parsed.deriver.is_none());
+        assert_eq!(parsed.to_string(), EXPLICIT_UNKNOWN_DERIVER);
+    }
+
+    #[test]
+    fn signatures_by() {
+        let parsed = NarInfo::parse(EXPLICIT_UNKNOWN_DERIVER).expect("should parse");
+
+        let signatures: Vec<_> = parsed.signatures_by("cache.nixos.org-1").collect();
+        assert_eq!(signatures.len(), 1);
+        assert_eq!(*signatures[0].name(), "cache.nixos.org-1");
+        assert_eq!(parsed.signatures_by("hydra.other.net-1").count(), 1);
+        assert_eq!(parsed.signatures_by("cache.nixos.org").count(), 0);
+    }
+
+    #[test]
+    fn deriver_absolute() {
+        // This doesn't exist as such in cache.nixos.org.
+        // We explicitly prefixed the Deriver field with the store dir (and
+        // nixbase32-encoded the NarHash) for the sake of this test.
+        let input = r#"StorePath: /nix/store/0vpqfxbkx0ffrnhbws6g9qwhmliksz7f-perl-HTTP-Cookies-6.01
+URL: nar/1rv1m9inydm1r4krw8hmwg1hs86d0nxddd1pbhihx7l7fycjvfk3.nar.xz
+Compression: xz
+FileHash: sha256:1rv1m9inydm1r4krw8hmwg1hs86d0nxddd1pbhihx7l7fycjvfk3
+FileSize: 19912
+NarHash: sha256:1wsgz5cbawm6ifh59ccixw4n717wng5n6wp4sxyav0ad78lzvbb0
+NarSize: 45840
+References: 0vpqfxbkx0ffrnhbws6g9qwhmliksz7f-perl-HTTP-Cookies-6.01 9vrhbib2lxd9pjlg6fnl5b82gblidrcr-perl-HTTP-Message-6.06 wy20zslqxzxxfpzzk0rajh41d7a6mlnf-perl-HTTP-Date-6.02
+Deriver: /nix/store/fb4ihlq3psnsjq95mvvs49rwpplpc8zj-perl-HTTP-Cookies-6.01.drv
+Sig: cache.nixos.org-1:HhaiY36Uk3XV1JGe9d9xHnzAapqJXprU1YZZzSzxE97jCuO5RR7vlG2kF7MSC5thwRyxAtdghdSz3AqFi+QSCw==
+"#;
+        let parsed = NarInfo::parse(input).expect("should parse");
+
+        assert!(parsed.flags.contains(Flags::DERIVER_ABSOLUTE));
+        assert_eq!(
+            "fb4ihlq3psnsjq95mvvs49rwpplpc8zj-perl-HTTP-Cookies-6.01",
+            parsed.deriver.as_ref().expect("must be some").to_string()
+        );
         assert_eq!(parsed.to_string(), input);
     }
 
@@ -652,6 +958,36 @@ Sig: cache.nixos.org-1:WDvKIdxSnQ8p2w9SD0ffdibUSNMz6QQN6jpe+A8LLNHmZFsX+m8GZF0x9
         assert_eq!(parsed.to_string(), input);
     }
 
+    #[test]
+    fn check_references() {
+        let parsed = NarInfo::parse(r#"StorePath: /nix/store/0vpqfxbkx0ffrnhbws6g9qwhmliksz7f-perl-HTTP-Cookies-6.01
+URL: nar/1rv1m9inydm1r4krw8hmwg1hs86d0nxddd1pbhihx7l7fycjvfk3.nar.xz
+Compression: xz
+FileHash: sha256:1rv1m9inydm1r4krw8hmwg1hs86d0nxddd1pbhihx7l7fycjvfk3
+FileSize: 19912
+NarHash: sha256:1wsgz5cbawm6ifh59ccixw4n717wng5n6wp4sxyav0ad78lzvbb0
+NarSize: 45840
+References: 0vpqfxbkx0ffrnhbws6g9qwhmliksz7f-perl-HTTP-Cookies-6.01 9vrhbib2lxd9pjlg6fnl5b82gblidrcr-perl-HTTP-Message-6.06 wy20zslqxzxxfpzzk0rajh41d7a6mlnf-perl-HTTP-Date-6.02
+Deriver: fb4ihlq3psnsjq95mvvs49rwpplpc8zj-perl-HTTP-Cookies-6.01.drv
+Sig: cache.nixos.org-1:HhaiY36Uk3XV1JGe9d9xHnzAapqJXprU1YZZzSzxE97jCuO5RR7vlG2kF7MSC5thwRyxAtdghdSz3AqFi+QSCw==
+"#).expect("should parse");
+
+        assert_eq!(parsed.check_references(|_| true), Ok(()));
+
+        let missing = parsed
+            .check_references(|reference| *reference.name() != "perl-HTTP-Message-6.06")
+            .expect_err("must report the missing reference");
+        assert_eq!(
+            missing,
+            vec![
+                StorePathRef::from_bytes(
+                    b"9vrhbib2lxd9pjlg6fnl5b82gblidrcr-perl-HTTP-Message-6.06"
+                )
+                .unwrap()
+            ]
+        );
+    }
+
     /// Adds a signature to a NARInfo, using key material parsed from DUMMY_KEYPAIR.
     /// It then ensures signature verification with the parsed
     /// DUMMY_VERIFYING_KEY succeeds.
@@ -693,4 +1029,82 @@ CA: fixed:r:sha1:1ak1ymbmsfx7z8kh09jzkr3a4dvkrfjw
             "expect signature to be valid"
         );
     }
+
+    #[test]
+    fn from_derivation_output() {
+        let drv = Derivation::from_aterm_bytes(include_bytes!(
+            "../derivation/tests/derivation_tests/ok/4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo.drv"
+        ))
+        .expect("must parse");
+        let drv_path =
+            StorePathRef::from_bytes(b"4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo.drv").unwrap();
+        let bar_drv_path =
+            StorePathRef::from_bytes(b"0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv").unwrap();
+        let bar = StorePathRef::from_bytes(b"4q0pg5zpfmznxscq3avycvf9xdvx50n3-bar").unwrap();
+
+        let narinfo =
+            NarInfo::from_derivation_output(&drv, &drv_path, "out", [0; 32], 42, |path, output| {
+                assert_eq!(path, bar_drv_path);
+                assert_eq!(output, "out");
+                Some(bar.clone())
+            })
+            .expect("must succeed");
+
+        assert_eq!(
+            "/nix/store/5vyvcwah9l9kf07d52rcgdk70g2f4y13-foo",
+            narinfo.store_path.to_absolute_path()
+        );
+        assert_eq!(vec![bar.clone()], narinfo.references);
+        assert_eq!(
+            Some(StorePathRef::from_bytes(b"4wvvbi4jwn0prsdxb7vs673qa5h9gr7x-foo").unwrap()),
+            narinfo.deriver
+        );
+        assert_eq!(Some(":"), narinfo.system);
+        assert_eq!(None, narinfo.ca);
+        assert_eq!(42, narinfo.nar_size);
+
+        let lookup_bar = |_, _: &str| Some(bar.clone());
+        assert!(matches!(
+            NarInfo::from_derivation_output(&drv, &drv_path, "dev", [0; 32], 42, lookup_bar),
+            Err(Error::MissingOutput(_))
+        ));
+        assert!(matches!(
+            NarInfo::from_derivation_output(&drv, &drv_path, "out", [0; 32], 42, |_, _| None),
+            Err(Error::UnresolvedInputOutput(_, _))
+        ));
+        assert!(matches!(
+            NarInfo::from_derivation_output(&drv, &bar, "out", [0; 32], 42, lookup_bar),
+            Err(Error::InvalidDeriverStorePathMissingSuffix)
+        ));
+    }
+
+    #[test]
+    fn from_fixed_output_derivation_output() {
+        let drv = Derivation::from_aterm_bytes(include_bytes!(
+            "../derivation/tests/derivation_tests/ok/0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv"
+        ))
+        .expect("must parse");
+        let drv_path =
+            StorePathRef::from_bytes(b"0hm2f1psjpcwg8fijsmr4wwxrx59s092-bar.drv").unwrap();
+        let nar_hash = hex!("08813cbee9903c62be4c5027726a418a300da4500b2d369d3af9286f4815ceba");
+
+        let narinfo =
+            NarInfo::from_derivation_output(&drv, &drv_path, "out", nar_hash, 42, |_, _| None)
+                .expect("must succeed");
+        assert!(narinfo.references.is_empty());
+        assert_eq!(Some(CAHash::Nar(NixHash::Sha256(nar_hash))), narinfo.ca);
+
+        assert!(matches!(
+            NarInfo::from_derivation_output(&drv, &drv_path, "out", [0; 32], 42, |_, _| None),
+            Err(Error::NarHashCAMismatch)
+        ));
+
+        // A content address not matching the output path is rejected.
+        let mut drv = drv;
+        drv.outputs.get_mut("out").unwrap().ca_hash = Some(CAHash::Nar(NixHash::Sha256([0; 32])));
+        assert!(matches!(
+            NarInfo::from_derivation_output(&drv, &drv_path, "out", [0; 32], 42, |_, _| None),
+            Err(Error::StorePathCAMismatch(_))
+        ));
+    }
 }
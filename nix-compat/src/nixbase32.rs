@@ -124,6 +124,11 @@ fn decode_inner(input: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
     Ok(())
 }
 
+/// Returns whether the given character is part of the nixbase32 alphabet.
+pub(crate) fn is_valid_char(c: u8) -> bool {
+    BASE32_ORD[c as usize] != 0xFF
+}
+
 fn find_invalid(input: &[u8]) -> usize {
     for (i, &c) in input.iter().enumerate() {
         if !ALPHABET.contains(&c) {
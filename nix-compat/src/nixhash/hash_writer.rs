@@ -0,0 +1,70 @@
+use sha2::Digest;
+use sha2::digest::Output;
+use std::io::{self, Write};
+
+/// [Write] adapter passing all bytes on to an inner writer, while also
+/// feeding them into a hash function.
+///
+/// This is the write-side counterpart of hashing the bytes read from a
+/// reader, and is useful to e.g. calculate the NAR hash of a NAR while
+/// it's being written somewhere.
+pub struct HashWriter<W, D> {
+    inner: W,
+    digest: D,
+}
+
+impl<W: Write, D: Digest> HashWriter<W, D> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            digest: D::new(),
+        }
+    }
+
+    /// Returns a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Consumes the writer, returning the inner writer and the digest
+    /// of all bytes written so far.
+    pub fn finalize(self) -> (W, Output<D>) {
+        (self.inner, self.digest.finalize())
+    }
+}
+
+impl<W: Write, D: Digest> Write for HashWriter<W, D> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        // Only hash what the inner writer actually accepted.
+        self.digest.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HashWriter;
+    use hex_literal::hex;
+    use sha2::Sha256;
+    use std::io::Write;
+
+    #[test]
+    fn tee_vec_and_sha256() {
+        let mut writer = HashWriter::<Vec<u8>, Sha256>::new(Vec::new());
+        writer.write_all(b"Hello").expect("must succeed");
+        writer.write_all(b" World").expect("must succeed");
+        assert_eq!(b"Hello World", writer.get_ref().as_slice());
+
+        let (buf, digest) = writer.finalize();
+        assert_eq!(b"Hello World", buf.as_slice());
+        assert_eq!(
+            hex!("a591a6d40bf420404a011733cfb7b190d62c65bf0bcda32b57b277d9ad9f146e"),
+            digest.as_slice()
+        );
+    }
+}
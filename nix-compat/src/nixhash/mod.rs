@@ -9,10 +9,12 @@ use thiserror;
 
 mod algos;
 mod ca_hash;
+mod hash_writer;
 
 pub use algos::HashAlgo;
 pub use ca_hash::CAHash;
 pub use ca_hash::HashMode as CAHashMode;
+pub use hash_writer::HashWriter;
 
 /// NixHash represents hashes known by Nix.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -75,6 +77,24 @@ impl NixHash {
         }
     }
 
+    /// Compares two hashes like `==`, but in time independent of the
+    /// digest contents, for use when verifying untrusted hashes.
+    /// Hashes of different algos are never equal.
+    pub fn ct_eq(&self, other: &NixHash) -> bool {
+        if self.algo() != other.algo() {
+            return false;
+        }
+
+        let diff = self
+            .digest_as_bytes()
+            .iter()
+            .zip(other.digest_as_bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b));
+
+        // Keep the compiler from turning this back into an early return.
+        std::hint::black_box(diff) == 0
+    }
+
     /// Constructs a [NixHash] from the Nix default hash format,
     /// the inverse of [Self::to_nix_hex_string].
     pub fn from_nix_hex_str(s: &str) -> Option<Self> {
@@ -598,6 +618,20 @@ mod tests {
         nixhash::from_str(weird_base64, Some("sha256")).expect_err("must fail");
     }
 
+    #[rstest]
+    #[case::equal(NixHash::Sha256(DIGEST_SHA256), NixHash::Sha256(DIGEST_SHA256))]
+    #[case::different_digest(NixHash::Sha1(DIGEST_SHA1), NixHash::Sha1([0; 20]))]
+    #[case::different_algo(NixHash::Md5(DIGEST_MD5), NixHash::Sha1(DIGEST_SHA1))]
+    #[case::sha512(
+        NixHash::Sha512(Box::new(DIGEST_SHA512)),
+        NixHash::Sha512(Box::new(DIGEST_SHA512))
+    )]
+    fn ct_eq(#[case] a: NixHash, #[case] b: NixHash) {
+        assert_eq!(a == b, a.ct_eq(&b));
+        assert_eq!(b == a, b.ct_eq(&a));
+        assert!(a.ct_eq(&a));
+    }
+
     #[test]
     fn serialize_deserialize() {
         let nixhash_actual = NixHash::Sha256(hex!(
@@ -0,0 +1,38 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Deduplicates store path names, so all store paths interned with the same
+/// [Interner] share a single allocation per distinct name.
+///
+/// This is useful when holding on to a lot of store paths with repeated
+/// names, e.g. the references of many narinfo files.
+/// See [super::StorePath::intern].
+#[derive(Debug, Default)]
+pub struct Interner(HashSet<Arc<str>>);
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shared allocation for the given name, creating it if
+    /// this name wasn't interned before.
+    pub fn intern(&mut self, name: &str) -> Arc<str> {
+        if let Some(interned) = self.0.get(name) {
+            return interned.clone();
+        }
+
+        let interned: Arc<str> = name.into();
+        self.0.insert(interned.clone());
+        interned
+    }
+
+    /// Number of distinct names interned.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
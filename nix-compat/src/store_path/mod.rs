@@ -5,11 +5,16 @@ use std::{
     fmt,
     path::Path,
     str::{self, FromStr},
+    sync::Arc,
 };
 use thiserror;
 
+mod interner;
+mod reference_scanner;
 mod utils;
 
+pub use interner::Interner;
+pub use reference_scanner::ReferenceScanner;
 pub use utils::*;
 
 pub const DIGEST_SIZE: usize = 20;
@@ -20,6 +25,9 @@ pub const ENCODED_DIGEST_SIZE: usize = nixbase32::encode_len(DIGEST_SIZE);
 pub const STORE_DIR: &str = "/nix/store";
 pub const STORE_DIR_WITH_SLASH: &str = "/nix/store/";
 
+/// The maximum length of the name of a store path, as enforced by Nix.
+pub const MAX_NAME_LEN: usize = 211;
+
 /// Errors that can occur when parsing a literal store path
 #[derive(Debug, PartialEq, Eq, thiserror::Error)]
 pub enum Error {
@@ -29,6 +37,8 @@ pub enum Error {
     InvalidHashEncoding(#[from] DecodeError),
     #[error("Invalid length")]
     InvalidLength,
+    #[error("Name is {0} characters long, but at most {MAX_NAME_LEN} are allowed")]
+    NameTooLong(usize),
     #[error(
         "Invalid name: \"{}\", character at position {} is invalid",
         std::str::from_utf8(.0).unwrap_or(&BASE64.encode(.0)),
@@ -81,6 +91,10 @@ where
 /// Used by [StorePath] for parsing.
 pub type StorePathRef<'a> = StorePath<&'a str>;
 
+/// Like [StorePath], but with a reference-counted name, which can be shared
+/// with other store paths through an [Interner].
+pub type InternedStorePath = StorePath<Arc<str>>;
+
 impl<S> StorePath<S>
 where
     S: AsRef<str>,
@@ -89,6 +103,12 @@ where
         &self.digest
     }
 
+    /// Returns the digest in its [crate::nixbase32]-encoded form, as it
+    /// appears in the string representation of the store path.
+    pub fn digest_str(&self) -> String {
+        nixbase32::encode(&self.digest)
+    }
+
     pub fn name(&self) -> &S {
         &self.name
     }
@@ -107,6 +127,15 @@ where
         }
     }
 
+    /// Converts to an [InternedStorePath], sharing the name with all other
+    /// store paths of the same name interned with the given [Interner].
+    pub fn intern(&self, interner: &mut Interner) -> InternedStorePath {
+        StorePath {
+            digest: self.digest,
+            name: interner.intern(self.name.as_ref()),
+        }
+    }
+
     /// Construct a [StorePath] by passing the `$digest-$name` string
     /// that comes after [STORE_DIR_WITH_SLASH].
     pub fn from_bytes<'a>(s: &'a [u8]) -> Result<Self, Error>
@@ -174,6 +203,8 @@ where
 
     /// Decompose a string into a [StorePath] and a [Path] containing the
     /// rest of the path, or an error.
+    /// Trailing slashes are not part of the returned rest, so
+    /// `/nix/store/$hash-name/` yields an empty rest.
     #[cfg(target_family = "unix")]
     pub fn from_absolute_path_full<'a, P>(path: &'a P) -> Result<(Self, &'a Path), Error>
     where
@@ -295,9 +326,12 @@ pub(crate) fn validate_name(s: &(impl AsRef<[u8]> + ?Sized)) -> Result<&str, Err
     let s = s.as_ref();
 
     // Empty or excessively long names are not allowed.
-    if s.is_empty() || s.len() > 211 {
+    if s.is_empty() {
         return Err(Error::InvalidLength);
     }
+    if s.len() > MAX_NAME_LEN {
+        return Err(Error::NameTooLong(s.len()));
+    }
 
     let mut valid = true;
     for &c in s {
@@ -326,12 +360,7 @@ where
     /// bytes), [crate::nixbase32]-encoded, followed by a `-`,
     /// and ends with the name.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}-{}",
-            nixbase32::encode(&self.digest),
-            self.name.as_ref()
-        )
+        write!(f, "{}-{}", self.digest_str(), self.name.as_ref())
     }
 }
 
@@ -341,7 +370,7 @@ mod tests {
     use std::cmp::Ordering;
     use std::path::PathBuf;
 
-    use crate::store_path::{DIGEST_SIZE, StorePath, StorePathRef};
+    use crate::store_path::{DIGEST_SIZE, Interner, MAX_NAME_LEN, StorePath, StorePathRef};
     use hex_literal::hex;
     use pretty_assertions::assert_eq;
     use rstest::rstest;
@@ -370,6 +399,45 @@ mod tests {
         assert_eq!(example_nix_path_str, nixpath.to_string())
     }
 
+    #[test]
+    fn intern() {
+        let a = StorePathRef::from_bytes(b"00bgd045z0d4icpbc2yyz4gx48ak44la-net-tools")
+            .expect("must parse");
+        let b = StorePathRef::from_bytes(b"1k8z4ax0i1n6f3ipqd0cg6d5x24g0ssq-net-tools")
+            .expect("must parse");
+        let c = StorePathRef::from_bytes(b"00bgd045z0d4icpbc2yyz4gx48ak44la-coreutils")
+            .expect("must parse");
+
+        let mut interner = Interner::new();
+        let a_interned = a.intern(&mut interner);
+        let b_interned = b.intern(&mut interner);
+        let c_interned = c.intern(&mut interner);
+
+        assert!(std::sync::Arc::ptr_eq(a_interned.name(), b_interned.name()));
+        assert!(!std::sync::Arc::ptr_eq(
+            a_interned.name(),
+            c_interned.name()
+        ));
+        assert_eq!(2, interner.len());
+
+        assert_eq!(a, a_interned.as_ref());
+        assert_eq!(b, b_interned.as_ref());
+    }
+
+    #[test]
+    fn digest_str() {
+        let example_nix_path_str =
+            "00bgd045z0d4icpbc2yyz4gx48ak44la-net-tools-1.60_p20170221182432";
+        let nixpath = StorePathRef::from_bytes(example_nix_path_str.as_bytes())
+            .expect("Error parsing example string");
+
+        assert_eq!("00bgd045z0d4icpbc2yyz4gx48ak44la", nixpath.digest_str());
+        assert_eq!(
+            example_nix_path_str,
+            format!("{}-{}", nixpath.digest_str(), nixpath.name())
+        );
+    }
+
     #[test]
     fn store_path_ordering() {
         let store_paths = [
@@ -574,7 +642,7 @@ mod tests {
         StorePath::from_bytes(b"00bgd045z0d4icpbc2yyz4gx48ak44la-net-tools-1.60_p20170221182432").unwrap(), PathBuf::from("bin/arp"))]
     #[case::with_prefix_and_trailing_slash(
         "/nix/store/00bgd045z0d4icpbc2yyz4gx48ak44la-net-tools-1.60_p20170221182432/bin/arp/",
-        StorePath::from_bytes(b"00bgd045z0d4icpbc2yyz4gx48ak44la-net-tools-1.60_p20170221182432").unwrap(), PathBuf::from("bin/arp/"))]
+        StorePath::from_bytes(b"00bgd045z0d4icpbc2yyz4gx48ak44la-net-tools-1.60_p20170221182432").unwrap(), PathBuf::from("bin/arp"))]
     fn from_absolute_path_full(
         #[case] s: &str,
         #[case] exp_store_path: StorePath<&str>,
@@ -585,6 +653,22 @@ mod tests {
 
         assert_eq!(exp_store_path, actual_store_path);
         assert_eq!(exp_path, actual_path);
+        // [Path] equality ignores trailing slashes, compare the raw rest too.
+        assert_eq!(exp_path.as_os_str(), actual_path.as_os_str());
+    }
+
+    #[test]
+    fn name_length() {
+        let digest = [0; DIGEST_SIZE];
+
+        let name = "a".repeat(MAX_NAME_LEN);
+        StorePathRef::from_name_and_digest_fixed(&name, digest).expect("must succeed");
+
+        let name = "a".repeat(MAX_NAME_LEN + 1);
+        assert_eq!(
+            Error::NameTooLong(MAX_NAME_LEN + 1),
+            StorePathRef::from_name_and_digest_fixed(&name, digest).expect_err("must fail")
+        );
     }
 
     #[test]
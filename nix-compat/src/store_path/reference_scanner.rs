@@ -0,0 +1,150 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{DIGEST_SIZE, ENCODED_DIGEST_SIZE};
+use crate::nixbase32;
+
+/// Scans arbitrary content for occurrences of a set of candidate store path
+/// digests, in their nixbase32 encoding, like Nix does to determine the
+/// references of build outputs.
+///
+/// Content can be fed in chunks of any size using [ReferenceScanner::scan],
+/// digests straddling chunk boundaries are still found.
+#[derive(Debug)]
+pub struct ReferenceScanner {
+    /// Candidate digests, keyed by their nixbase32 encoding.
+    candidates: HashMap<[u8; ENCODED_DIGEST_SIZE], [u8; DIGEST_SIZE]>,
+    found: HashSet<[u8; DIGEST_SIZE]>,
+    /// Trailing bytes of the content scanned so far, which may be the start
+    /// of a digest continuing in the next chunk.
+    tail: Vec<u8>,
+}
+
+impl ReferenceScanner {
+    pub fn new(candidates: impl IntoIterator<Item = [u8; DIGEST_SIZE]>) -> Self {
+        let candidates = candidates
+            .into_iter()
+            .map(|digest| {
+                let encoded = nixbase32::encode(&digest)
+                    .into_bytes()
+                    .try_into()
+                    .expect("encoded digest has a fixed size");
+                (encoded, digest)
+            })
+            .collect();
+
+        Self {
+            candidates,
+            found: HashSet::new(),
+            tail: Vec::new(),
+        }
+    }
+
+    /// Scans the next chunk of content.
+    pub fn scan(&mut self, chunk: &[u8]) {
+        const MAX_PARTIAL: usize = ENCODED_DIGEST_SIZE - 1;
+
+        // Digests straddling the boundary to the previous chunk start in the
+        // tail, and end within the first bytes of this chunk.
+        let mut tail = std::mem::take(&mut self.tail);
+        tail.extend_from_slice(&chunk[..chunk.len().min(MAX_PARTIAL)]);
+        self.scan_slice(&tail);
+        self.scan_slice(chunk);
+
+        // Keep the last bytes, which may be the start of a digest.
+        if chunk.len() >= MAX_PARTIAL {
+            tail.clear();
+            tail.extend_from_slice(&chunk[chunk.len() - MAX_PARTIAL..]);
+        } else {
+            tail.drain(..tail.len().saturating_sub(MAX_PARTIAL));
+        }
+        self.tail = tail;
+    }
+
+    /// Returns the candidate digests found in the scanned content.
+    pub fn finish(self) -> HashSet<[u8; DIGEST_SIZE]> {
+        self.found
+    }
+
+    fn scan_slice(&mut self, data: &[u8]) {
+        let mut i = 0;
+        while let Some(window) = data.get(i..i + ENCODED_DIGEST_SIZE) {
+            // Like Nix, skip past the last character which can't be part of
+            // an encoded digest.
+            if let Some(j) = window.iter().rposition(|&c| !nixbase32::is_valid_char(c)) {
+                i += j + 1;
+                continue;
+            }
+
+            if let Some(digest) = self.candidates.get(window) {
+                self.found.insert(*digest);
+            }
+
+            i += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::ReferenceScanner;
+    use crate::nixbase32;
+    use crate::store_path::{DIGEST_SIZE, StorePathRef};
+
+    const FOO: &str = "/nix/store/00bgd045z0d4icpbc2yyz4gx48ak44la-foo";
+    const BAR: &str = "/nix/store/4q0pg5zpfmznxscq3avycvf9xdvx50n3-bar";
+    const BAZ: &str = "/nix/store/5vyvcwah9l9kf07d52rcgdk70g2f4y13-baz";
+
+    fn digest(path: &str) -> [u8; DIGEST_SIZE] {
+        *StorePathRef::from_absolute_path(path.as_bytes())
+            .expect("must parse")
+            .digest()
+    }
+
+    fn scanner() -> ReferenceScanner {
+        ReferenceScanner::new([digest(FOO), digest(BAR), digest(BAZ)])
+    }
+
+    #[test]
+    fn scan() {
+        let mut scanner = scanner();
+        scanner.scan(format!("#!{BAR}/bin/sh\necho {FOO}\n").as_bytes());
+
+        assert_eq!(HashSet::from([digest(FOO), digest(BAR)]), scanner.finish());
+    }
+
+    #[test]
+    fn scan_split_digest() {
+        let content = format!("PATH={FOO}/bin:{BAR}/bin");
+        let (first, second) = content.split_at(content.find("z0d4").unwrap());
+
+        let mut scanner = scanner();
+        scanner.scan(first.as_bytes());
+        scanner.scan(second.as_bytes());
+
+        assert_eq!(HashSet::from([digest(FOO), digest(BAR)]), scanner.finish());
+    }
+
+    #[test]
+    fn scan_bytewise() {
+        let content = format!("{FOO}{BAZ}");
+
+        let mut scanner = scanner();
+        for b in content.as_bytes() {
+            scanner.scan(&[*b]);
+        }
+
+        assert_eq!(HashSet::from([digest(FOO), digest(BAZ)]), scanner.finish());
+    }
+
+    #[test]
+    fn scan_unknown_digest() {
+        let unknown = nixbase32::encode(&[0xaa; DIGEST_SIZE]);
+
+        let mut scanner = scanner();
+        scanner.scan(format!("/nix/store/{unknown}-foo").as_bytes());
+
+        assert!(scanner.finish().is_empty());
+    }
+}
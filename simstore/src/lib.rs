@@ -13,32 +13,142 @@
 //! of the following components:
 //!
 //! - [`SimulatedStoreIO`] implements the `EvalIO` trait and handles calculation of the store
-//!   paths for files that would need to be imported into the store.
-//! - The necessary additional builtins haven't been implemented yet.
+//!   paths for files that would need to be imported into the store. [`SharedSimulatedStoreIO`]
+//!   is a `Send + Sync` variant of it for evaluating on multiple threads.
+//! - [`RecordingStoreIO`] wraps another `EvalIO` implementation and records all operations
+//!   performed through it, e.g. to assert which store interactions an expression performs.
+//! - [`simulated_store_builtins`] returns builtins which need to interact with the simulated store,
+//!   e.g. to turn reads from unrealised store paths into catchable errors.
+//! - [`simulated_env_builtins`] returns a `builtins.getEnv` that only exposes the environment
+//!   variables allowed on a [`SimulatedStoreIO`].
 use std::borrow::Cow;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fs;
-use std::io::{BufReader, Error, Read, Result};
+use std::io::{BufReader, Error, Read, Result, Write};
 use std::iter::Peekable;
 use std::os::unix::ffi::OsStringExt;
 use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
 
 use nix_compat::{
     nar,
-    nixhash::{CAHash, NixHash},
-    store_path::{StorePath, build_ca_path},
+    nixhash::{CAHash, HashAlgo, NixHash},
+    store_path::{ENCODED_DIGEST_SIZE, MAX_NAME_LEN, StorePath, build_ca_path},
 };
-use sha2::{Digest, Sha256};
-use tvix_eval::{ErrorKind, EvalIO, FileType, StdIO, Value, builtin_macros::builtins};
+use sha2::digest::DynDigest;
+use sha2::{Digest, Sha256, Sha512};
+use tvix_eval::{
+    CatchableErrorKind, ErrorKind, EvalIO, FileType, NixAttrs, StdIO, Value,
+    builtin_macros::builtins,
+};
+
+/// Storage for the passthru paths of a [`GenericSimulatedStoreIO`], mapping store path digests to
+/// the locations on the filesystem that reads from the store path are forwarded to.
+pub trait PassthruPaths: Default {
+    /// Trait object type of the fallback [`EvalIO`] of a [`GenericSimulatedStoreIO`] using these
+    /// passthru paths, so that it is `Send + Sync` whenever the passthru paths are.
+    type FallbackIO: EvalIO + ?Sized;
+
+    fn get(&self, digest: &[u8; 20]) -> Option<PathBuf>;
+    fn insert(&self, digest: [u8; 20], loc: PathBuf);
+
+    /// Returns the fallback IO used unless another one is configured, i.e. [`StdIO`].
+    fn std_io() -> Arc<Self::FallbackIO>;
+}
+
+/// Single-threaded passthru paths. Cloning these copies the contained mappings.
+pub type LocalPassthruPaths = RefCell<HashMap<[u8; 20], PathBuf>>;
+
+/// Thread-safe passthru paths. Clones share the contained mappings.
+pub type SharedPassthruPaths = Arc<RwLock<HashMap<[u8; 20], PathBuf>>>;
+
+impl PassthruPaths for LocalPassthruPaths {
+    type FallbackIO = dyn EvalIO;
+
+    fn get(&self, digest: &[u8; 20]) -> Option<PathBuf> {
+        self.borrow().get(digest).cloned()
+    }
+
+    fn insert(&self, digest: [u8; 20], loc: PathBuf) {
+        self.borrow_mut().insert(digest, loc);
+    }
+
+    fn std_io() -> Arc<Self::FallbackIO> {
+        Arc::new(StdIO)
+    }
+}
 
-pub struct SimulatedStoreIO {
+impl PassthruPaths for SharedPassthruPaths {
+    type FallbackIO = dyn EvalIO + Send + Sync;
+
+    fn get(&self, digest: &[u8; 20]) -> Option<PathBuf> {
+        self.read()
+            .expect("passthru paths lock poisoned")
+            .get(digest)
+            .cloned()
+    }
+
+    fn insert(&self, digest: [u8; 20], loc: PathBuf) {
+        self.write()
+            .expect("passthru paths lock poisoned")
+            .insert(digest, loc);
+    }
+
+    fn std_io() -> Arc<Self::FallbackIO> {
+        Arc::new(StdIO)
+    }
+}
+
+/// Simulated store, generic over how its passthru paths are stored. Usually, either
+/// [`SimulatedStoreIO`] or [`SharedSimulatedStoreIO`] should be used.
+#[derive(Clone)]
+pub struct GenericSimulatedStoreIO<P: PassthruPaths> {
     store_dir: String,
-    passthru_paths: RefCell<HashMap<[u8; 20], PathBuf>>,
+    passthru_paths: P,
+    allow_env_vars: HashSet<String>,
+    import_options: ImportOptions,
+    forbid_real_store: bool,
+    fallback_io: Arc<P::FallbackIO>,
+}
+
+/// Tuning for walking the trees imported by [`EvalIO::import_path`].
+#[derive(Clone, Debug)]
+pub struct ImportOptions {
+    /// Maximum number of directories kept open at once while walking a tree, see
+    /// [`walkdir::WalkDir::max_open`].
+    pub max_open: usize,
+
+    /// Maximum depth of imported trees, with the imported path itself at depth 0. Importing a
+    /// deeper tree fails instead of silently omitting its deeper parts.
+    pub max_depth: Option<usize>,
+
+    /// Scan the contents of imported trees for references to store paths, which are returned by
+    /// [`GenericSimulatedStoreIO::import_path_with_references`]. The references don't influence
+    /// the store path of the import.
+    pub scan_references: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            max_open: 10,
+            max_depth: None,
+            scan_references: false,
+        }
+    }
 }
 
+/// Simulated store for use on a single thread, which is the common case.
+pub type SimulatedStoreIO = GenericSimulatedStoreIO<LocalPassthruPaths>;
+
+/// Simulated store which is `Send + Sync`. Clones of it share their passthru paths, so paths
+/// imported on one thread can be read from all others.
+pub type SharedSimulatedStoreIO = GenericSimulatedStoreIO<SharedPassthruPaths>;
+
 // TODO: copied from glue/import.rs; where should this live?
 fn path_to_name(path: &Path) -> std::io::Result<&str> {
     path.file_name()
@@ -51,7 +161,7 @@ fn path_to_name(path: &Path) -> std::io::Result<&str> {
         })
 }
 
-impl SimulatedStoreIO {
+impl<P: PassthruPaths> GenericSimulatedStoreIO<P> {
     /// Adds a passthru path, mapping the given path to the given location on the
     /// filesystem.
     ///
@@ -60,11 +170,67 @@ impl SimulatedStoreIO {
         let (store_path, _) =
             StorePath::<&str>::from_absolute_path_full(path).map_err(Error::other)?;
 
-        self.passthru_paths
-            .borrow_mut()
-            .insert(*store_path.digest(), loc);
+        self.passthru_paths.insert(*store_path.digest(), loc);
+        Ok(())
+    }
+
+    /// Adds all passthru paths listed in the manifest at the given path, which maps store paths
+    /// to locations on the filesystem, as if passed to [`Self::add_passthru`] one by one.
+    ///
+    /// The manifest is a TOML table if its file name ends in `.toml` and a JSON object otherwise.
+    /// Relative locations are resolved relative to the directory containing the manifest.
+    pub fn load_passthru_manifest(&mut self, path: &Path) -> Result<()> {
+        let contents = fs::read_to_string(path)?;
+        let entries: HashMap<String, PathBuf> = if path.extension().is_some_and(|ext| ext == "toml")
+        {
+            toml::from_str(&contents).map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?
+        } else {
+            serde_json::from_str(&contents)?
+        };
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        for (store_path, loc) in entries {
+            self.add_passthru(&store_path, base.join(loc))?;
+        }
+
         Ok(())
     }
+
+    /// Allows reading the given environment variable through the `builtins.getEnv` returned by
+    /// [`simulated_env_builtins`].
+    pub fn allow_env_var(&mut self, name: impl Into<String>) {
+        self.allow_env_vars.insert(name.into());
+    }
+
+    /// Sets the options used for walking the trees imported by [`EvalIO::import_path`].
+    pub fn set_import_options(&mut self, options: ImportOptions) {
+        self.import_options = options;
+    }
+
+    /// Sets whether reads from store paths that exist in the real Nix store on the local machine
+    /// are forbidden. By default, they are allowed, so only reads from store paths missing
+    /// locally result in [`SimulatedStoreError::StorePathRead`].
+    pub fn forbid_real_store(&mut self, forbid: bool) {
+        self.forbid_real_store = forbid;
+    }
+
+    /// Returns whether the given path is inside the store directory, i.e. is a store path or a
+    /// path within one. The store directory itself is not inside the store. The path is only
+    /// compared, not parsed, so it may still be an invalid store path.
+    pub fn is_store_path(&self, path: &Path) -> bool {
+        let store_dir = Path::new(&self.store_dir);
+        path.starts_with(store_dir) && path != store_dir
+    }
+
+    /// Sets the [`EvalIO`] that reads outside of the store directory, reads from passthru paths
+    /// and checks for store paths in the real Nix store are performed with. Defaults to
+    /// [`StdIO`].
+    pub fn with_fallback_io(self, fallback_io: Box<P::FallbackIO>) -> Self {
+        Self {
+            fallback_io: fallback_io.into(),
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -100,21 +266,27 @@ impl From<nix_compat::store_path::Error> for SimulatedStoreError {
     }
 }
 
-impl Default for SimulatedStoreIO {
+impl<P: PassthruPaths> Default for GenericSimulatedStoreIO<P> {
     fn default() -> Self {
         Self {
             store_dir: "/nix/store".to_owned(),
             passthru_paths: Default::default(),
+            allow_env_vars: Default::default(),
+            import_options: Default::default(),
+            forbid_real_store: false,
+            fallback_io: P::std_io(),
         }
     }
 }
 
 // TODO(sterni): creation with configurable store dir
-impl SimulatedStoreIO {
-    /// Returns a path from which StdIO can read, unless realisation is required
+impl<P: PassthruPaths> GenericSimulatedStoreIO<P> {
+    /// Returns a path from which the fallback IO can read, unless realisation is required
     /// (which the simulated store does not support).
     fn to_readable_path<'a>(&self, path: &'a Path) -> Result<Cow<'a, Path>> {
-        if !path.starts_with(Path::new(&self.store_dir)) {
+        // The store directory itself is no store path, but its contents depend on the store paths
+        // that happen to exist just as well, so reads from it are not passed through either.
+        if !self.is_store_path(path) && path != Path::new(&self.store_dir) {
             return Ok(Cow::Borrowed(path));
         }
 
@@ -122,55 +294,295 @@ impl SimulatedStoreIO {
             StorePath::<&str>::from_absolute_path_full(path).map_err(Error::other)?;
 
         // Pass known imported paths through to their original locations.
-        if let Some(base) = self.passthru_paths.borrow().get(store_path.digest()) {
+        if let Some(base) = self.passthru_paths.get(store_path.digest()) {
             return Ok(Cow::Owned(if relative.as_os_str().is_empty() {
-                base.into()
+                base
             } else {
                 base.join(relative)
             }));
         }
 
-        // Allow reads from the "real" Nix store locally.
+        // Allow reads from the "real" Nix store locally, unless forbidden.
         // We just check for the existence of the store path, so reads
         // from missing files inside the store path fail as expected
         // and pathExists can be used
-        if StdIO.path_exists(Path::new(&store_path.to_absolute_path()))? {
+        if !self.forbid_real_store
+            && self
+                .fallback_io
+                .path_exists(Path::new(&store_path.to_absolute_path()))?
+        {
             return Ok(Cow::Borrowed(path));
         }
 
         Err(Error::other(SimulatedStoreError::StorePathRead))
     }
 
+    /// Walks the tree at the given path in the order required for packing it into a NAR,
+    /// honouring the configured [`ImportOptions`].
+    fn walk_import(&self, path: &Path) -> impl Iterator<Item = Result<walkdir::DirEntry>> {
+        let max_depth = self.import_options.max_depth;
+        let mut walker = walkdir::WalkDir::new(path)
+            .follow_links(false)
+            .follow_root_links(false)
+            .contents_first(false)
+            .max_open(self.import_options.max_open)
+            .sort_by(|a, b| a.file_name().cmp(b.file_name()));
+
+        // Walk one level deeper than allowed to notice trees exceeding the limit.
+        if let Some(max_depth) = max_depth {
+            walker = walker.max_depth(max_depth.saturating_add(1));
+        }
+
+        walker.into_iter().map(move |entry| {
+            let entry = entry?;
+            match max_depth {
+                Some(max_depth) if entry.depth() > max_depth => Err(Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "{} exceeds the maximum import depth of {max_depth}",
+                        entry.path().display()
+                    ),
+                )),
+                _ => Ok(entry),
+            }
+        })
+    }
+
+    /// Imports the given path like [`EvalIO::import_path`]. If [`ImportOptions::scan_references`]
+    /// is set, the store paths referenced by the imported contents are returned as well, sorted
+    /// by their absolute path.
+    pub fn import_path_with_references(
+        &self,
+        path: &Path,
+    ) -> Result<(PathBuf, Option<Vec<StorePath<String>>>)> {
+        let path = path.canonicalize()?;
+        let mut hash = Sha256::new();
+        let mut entries = self.walk_import(&path).peekable();
+
+        let references = if self.import_options.scan_references {
+            let mut collector = StorePathCollector::new(&mut hash, &self.store_dir);
+            pack_entries(nar::writer::open(&mut collector)?, &mut entries)?;
+            Some(collector.finish())
+        } else {
+            pack_entries(nar::writer::open(&mut hash)?, &mut entries)?;
+            None
+        };
+
+        let name = path_to_name(&path)?;
+        let hash = CAHash::Nar(NixHash::Sha256(hash.finalize().into()));
+        let store_path: StorePath<&str> =
+            build_ca_path(name, &hash, Option::<&str>::default(), false).map_err(Error::other)?;
+
+        self.passthru_paths
+            .insert(*store_path.digest(), path.to_owned());
+
+        Ok((PathBuf::from(store_path.to_absolute_path()), references))
+    }
+
+    /// Calculates the store path the given entries would be imported at.
+    ///
+    /// Without an expected hash, the entries are imported recursively, using a SHA-256 NAR hash.
+    /// Otherwise, the ingestion method and hash algorithm are taken from the expected hash, and
+    /// the import fails if the actual hash differs from it. [`CAHash::Flat`] requires the entries
+    /// to consist of a single regular file.
     pub fn import_path_by_entries<I, E>(
         &self,
         name: &str,
         entries: I,
-        expected_sha256: Option<[u8; 32]>,
+        expected_hash: Option<CAHash>,
     ) -> Result<StorePath<String>>
     where
         Error: From<E>,
         I: Iterator<Item = std::result::Result<walkdir::DirEntry, E>>,
     {
-        let mut hash = Sha256::new();
-        let nar = nar::writer::open(&mut hash)?;
+        let mut entries = entries.peekable();
+
+        let actual_hash = match &expected_hash {
+            None => CAHash::Nar(nar_hash(HashAlgo::Sha256, &mut entries)?),
+            Some(CAHash::Nar(expected)) => CAHash::Nar(nar_hash(expected.algo(), &mut entries)?),
+            Some(CAHash::Flat(expected)) => CAHash::Flat(flat_hash(expected.algo(), &mut entries)?),
+            Some(CAHash::Text(_)) => {
+                return Err(Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "text hashes can not be used to import paths",
+                ));
+            }
+        };
+
+        if let Some(expected) = expected_hash {
+            // Both hashes use the same mode, see above.
+            if !actual_hash.hash().ct_eq(&expected.hash()) {
+                return Err(Error::other(format!(
+                    "hash mismatch importing {name}: expected {}, got {}",
+                    expected.hash(),
+                    actual_hash.hash(),
+                )));
+            }
+        }
+
+        build_ca_path(name, &actual_hash, Option::<String>::default(), false).map_err(Error::other)
+    }
+}
+
+/// [`Write`] adapter feeding everything written into a hash function for the given algo.
+struct NixHasher {
+    algo: HashAlgo,
+    digest: Box<dyn DynDigest>,
+}
+
+impl NixHasher {
+    fn new(algo: HashAlgo) -> Self {
+        let digest: Box<dyn DynDigest> = match algo {
+            HashAlgo::Md5 => Box::new(md5::Md5::new()),
+            HashAlgo::Sha1 => Box::new(sha1::Sha1::new()),
+            HashAlgo::Sha256 => Box::new(Sha256::new()),
+            HashAlgo::Sha512 => Box::new(Sha512::new()),
+        };
+
+        Self { algo, digest }
+    }
+
+    fn finalize(self) -> NixHash {
+        nix_compat::nixhash::from_algo_and_digest(self.algo, &self.digest.finalize())
+            .expect("digest length must match its algo")
+    }
+}
+
+impl Write for NixHasher {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.digest.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// [`Write`] adapter passing everything written on to an inner writer, while collecting all store
+/// paths (`<store_dir>/<digest>-<name>`) occurring in it.
+///
+/// Unlike [`nix_compat::store_path::ReferenceScanner`], which looks for a known set of candidate
+/// digests like Nix does for build outputs, this finds references to any store path. The
+/// simulated store doesn't know which store paths exist, so there are no candidates to match.
+struct StorePathCollector<W> {
+    inner: W,
+    /// The store directory, including a trailing slash.
+    needle: Vec<u8>,
+    /// Bytes written so far which may still be (the start of) a reference.
+    window: Vec<u8>,
+    references: HashSet<StorePath<String>>,
+}
+
+impl<W: Write> StorePathCollector<W> {
+    /// Maximum length of the `<digest>-<name>` part of a reference, plus a terminating byte.
+    const MAX_REFERENCE_LEN: usize = ENCODED_DIGEST_SIZE + 1 + MAX_NAME_LEN + 1;
+
+    fn new(inner: W, store_dir: &str) -> Self {
+        Self {
+            inner,
+            needle: format!("{store_dir}/").into_bytes(),
+            window: Vec::new(),
+            references: HashSet::new(),
+        }
+    }
+
+    /// Collects the references in the window. Unless `eof` is set, references which might
+    /// continue in later writes are kept in the window, along with a potential partial needle.
+    fn scan(&mut self, eof: bool) {
+        let mut keep_from = self.window.len().saturating_sub(self.needle.len());
+        let mut pos = 0;
 
-        pack_entries(nar, &mut entries.peekable())?;
+        while let Some(offset) = self.window[pos..]
+            .windows(self.needle.len())
+            .position(|w| w == self.needle)
+        {
+            let start = pos + offset;
+            let rest = &self.window[start + self.needle.len()..];
+            let end = rest
+                .iter()
+                .take(Self::MAX_REFERENCE_LEN)
+                .position(|&c| !(c.is_ascii_alphanumeric() || b"+-._?=".contains(&c)));
 
-        let nar_hash = NixHash::Sha256(hash.finalize().into());
+            let candidate = match end {
+                Some(end) => Some(&rest[..end]),
+                None if eof => Some(rest),
+                // Too long to be a reference.
+                None if rest.len() >= Self::MAX_REFERENCE_LEN => None,
+                None => {
+                    keep_from = keep_from.min(start);
+                    break;
+                }
+            };
 
-        if let Some(expected) = expected_sha256 {
-            if nar_hash != NixHash::Sha256(expected) {
-                // TODO: this error is really bad; needs both hashes etc.
-                // It doesn't feel like this is the right place.
-                return Err(Error::other("expected hash does not match"));
+            if let Some(Ok(store_path)) = candidate.map(StorePath::<String>::from_bytes) {
+                self.references.insert(store_path);
             }
+
+            pos = start + 1;
         }
 
-        let hash = CAHash::Nar(nar_hash);
-        build_ca_path(name, &hash, Option::<String>::default(), false).map_err(Error::other)
+        self.window.drain(..keep_from);
+    }
+
+    /// Returns all references found, sorted by their absolute path.
+    fn finish(mut self) -> Vec<StorePath<String>> {
+        self.scan(true);
+
+        let mut references: Vec<_> = self.references.into_iter().collect();
+        references.sort_by_key(|store_path| store_path.to_absolute_path());
+        references
     }
 }
 
+impl<W: Write> Write for StorePathCollector<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.window.extend_from_slice(&buf[..n]);
+        self.scan(false);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Hashes the NAR serialisation of the given entries.
+fn nar_hash<E, I>(algo: HashAlgo, entries: &mut Peekable<I>) -> Result<NixHash>
+where
+    Error: From<E>,
+    I: Iterator<Item = std::result::Result<walkdir::DirEntry, E>>,
+{
+    let mut hasher = NixHasher::new(algo);
+    let nar = nar::writer::open(&mut hasher)?;
+    pack_entries(nar, entries)?;
+
+    Ok(hasher.finalize())
+}
+
+/// Hashes the contents of the single regular file making up the given entries.
+fn flat_hash<E, I>(algo: HashAlgo, entries: &mut I) -> Result<NixHash>
+where
+    Error: From<E>,
+    I: Iterator<Item = std::result::Result<walkdir::DirEntry, E>>,
+{
+    let entry = match (entries.next().transpose()?, entries.next()) {
+        (Some(entry), None) if entry.file_type().is_file() => entry,
+        _ => {
+            return Err(Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "flat imports require a single regular file",
+            ));
+        }
+    };
+
+    let mut hasher = NixHasher::new(algo);
+    std::io::copy(&mut fs::File::open(entry.path())?, &mut hasher)?;
+
+    Ok(hasher.finalize())
+}
+
 fn pack_entries_dir<W, E, I>(
     mut dir: nar::writer::Directory<'_, W>,
     depth: usize,
@@ -224,6 +636,7 @@ where
 
     let ft = entry.file_type();
     if ft.is_symlink() {
+        // Symlink targets (like file names) are arbitrary bytes, don't assume they're UTF-8.
         let target = fs::read_link(entry.path())?.into_os_string();
         nar.symlink(target.into_vec().as_slice())?;
     } else if ft.is_file() {
@@ -246,65 +659,213 @@ where
     Ok(())
 }
 
-impl EvalIO for SimulatedStoreIO {
+impl<P: PassthruPaths> EvalIO for GenericSimulatedStoreIO<P> {
     fn store_dir(&self) -> Option<String> {
         Some(self.store_dir.clone())
     }
 
     fn import_path(&self, path: &Path) -> Result<PathBuf> {
-        let path = path.canonicalize()?;
-        let mut hash = Sha256::new();
-        let nar = nar::writer::open(&mut hash)?;
+        self.import_path_with_references(path)
+            .map(|(store_path, _)| store_path)
+    }
 
-        let walker = walkdir::WalkDir::new(path.clone())
-            .follow_links(false)
-            .follow_root_links(false)
-            .contents_first(false)
-            .sort_by(|a, b| a.file_name().cmp(b.file_name()))
-            .into_iter();
+    // TODO(sterni): proc macro for dispatching methods
+    fn path_exists(&self, path: &Path) -> Result<bool> {
+        self.fallback_io
+            .path_exists(self.to_readable_path(path)?.as_ref())
+    }
 
-        pack_entries(nar, &mut walker.peekable())?;
+    fn open(&self, path: &Path) -> Result<Box<dyn Read>> {
+        self.fallback_io.open(self.to_readable_path(path)?.as_ref())
+    }
 
-        let name = path_to_name(&path)?;
-        let hash = CAHash::Nar(NixHash::Sha256(hash.finalize().into()));
-        let store_path: StorePath<&str> =
-            build_ca_path(name, &hash, Option::<&str>::default(), false).map_err(Error::other)?;
+    fn file_type(&self, path: &Path) -> Result<FileType> {
+        self.fallback_io
+            .file_type(self.to_readable_path(path)?.as_ref())
+    }
 
-        self.passthru_paths
-            .borrow_mut()
-            .insert(*store_path.digest(), path.to_owned());
+    fn read_dir(&self, path: &Path) -> Result<Vec<(bytes::Bytes, FileType)>> {
+        self.fallback_io
+            .read_dir(self.to_readable_path(path)?.as_ref())
+    }
+}
+
+/// An operation performed through a [`RecordingStoreIO`], along with the path it was performed on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IoOp {
+    PathExists(PathBuf),
+    Open(PathBuf),
+    FileType(PathBuf),
+    ReadDir(PathBuf),
+    ImportPath(PathBuf),
+}
 
-        Ok(PathBuf::from(store_path.to_absolute_path()))
+/// Implements [`EvalIO`] by passing all operations through to the wrapped IO implementation,
+/// recording them in the order they were performed. Operations are recorded regardless of whether
+/// they succeeded.
+pub struct RecordingStoreIO<IO> {
+    inner: IO,
+    operations: RefCell<Vec<IoOp>>,
+}
+
+impl<IO: EvalIO> RecordingStoreIO<IO> {
+    pub fn new(inner: IO) -> Self {
+        Self {
+            inner,
+            operations: Default::default(),
+        }
+    }
+
+    /// Returns all operations recorded so far.
+    pub fn operations(&self) -> Vec<IoOp> {
+        self.operations.borrow().clone()
+    }
+
+    fn record(&self, op: IoOp) {
+        self.operations.borrow_mut().push(op);
+    }
+}
+
+impl<IO: EvalIO> EvalIO for RecordingStoreIO<IO> {
+    fn store_dir(&self) -> Option<String> {
+        self.inner.store_dir()
+    }
+
+    fn import_path(&self, path: &Path) -> Result<PathBuf> {
+        self.record(IoOp::ImportPath(path.to_owned()));
+        self.inner.import_path(path)
     }
 
-    // TODO(sterni): proc macro for dispatching methods
     fn path_exists(&self, path: &Path) -> Result<bool> {
-        StdIO.path_exists(self.to_readable_path(path)?.as_ref())
+        self.record(IoOp::PathExists(path.to_owned()));
+        self.inner.path_exists(path)
     }
 
     fn open(&self, path: &Path) -> Result<Box<dyn Read>> {
-        StdIO.open(self.to_readable_path(path)?.as_ref())
+        self.record(IoOp::Open(path.to_owned()));
+        self.inner.open(path)
     }
 
     fn file_type(&self, path: &Path) -> Result<FileType> {
-        StdIO.file_type(self.to_readable_path(path)?.as_ref())
+        self.record(IoOp::FileType(path.to_owned()));
+        self.inner.file_type(path)
     }
 
     fn read_dir(&self, path: &Path) -> Result<Vec<(bytes::Bytes, FileType)>> {
-        StdIO.read_dir(self.to_readable_path(path)?.as_ref())
+        self.record(IoOp::ReadDir(path.to_owned()));
+        self.inner.read_dir(path)
     }
 }
 
+/// Whether the given error was caused by attempting to read from an unrealised store path.
+fn is_store_path_read(error: &Error) -> bool {
+    error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<SimulatedStoreError>())
+        .is_some_and(|inner| *inner == SimulatedStoreError::StorePathRead)
+}
+
+/// Turns an I/O error encountered by a builtin into its result. Reads from unrealised store paths
+/// are turned into a catchable error, so that e.g. `builtins.tryEval` can recover from them. All
+/// other errors are fatal.
+fn io_error_to_result(path: PathBuf, error: Error) -> std::result::Result<Value, ErrorKind> {
+    if is_store_path_read(&error) {
+        return Ok(Value::from(CatchableErrorKind::UnimplementedFeature(
+            format!("reading from unrealised store path {}", path.display()).into(),
+        )));
+    }
+
+    Err(ErrorKind::IO {
+        path: Some(path),
+        error: Rc::new(error),
+    })
+}
+
+/// Nothing is ever downloaded, so `fetchurl` can only calculate the store path of files whose
+/// hash is given.
+const FETCHURL_WITHOUT_HASH: &str =
+    "fetchurl without sha256 requires a real store, unavailable in tvix-simstore";
+
+/// Returns the name `fetchurl` uses for the given URL if none is passed explicitly, i.e. the last
+/// segment of its path.
+fn url_basename(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+}
+
 // TODO(sterni): implement simulation, parse args
 // TODO(sterni): move derivationStrict simulation here
-#[builtins]
+#[builtins(state = "Rc<dyn EvalIO>")]
 mod builtins {
     use super::*;
-    use tvix_eval::generators::{Gen, GenCo};
+    use tvix_eval::builtins::coerce_value_to_path;
+    use tvix_eval::generators::{self, Gen, GenCo};
+
+    /// Simulates `builtins.fetchurl` by calculating the fixed-output path of the file and
+    /// returning it if it is available already, either as a passthru path or in the local store.
+    /// Nothing is ever downloaded, so the expected hash must be given.
+    #[builtin("fetchurl")]
+    async fn builtin_fetchurl(
+        state: Rc<dyn EvalIO>,
+        co: GenCo,
+        args: Value,
+    ) -> std::result::Result<Value, ErrorKind> {
+        let attrs = match args.to_attrs() {
+            Ok(attrs) => attrs,
+            // A plain URL carries no hash, so the path can't be calculated.
+            Err(_) => return Err(ErrorKind::UnsupportedByIO(FETCHURL_WITHOUT_HASH)),
+        };
+
+        let mut fields = HashMap::new();
+        for (key, value) in attrs.iter() {
+            let key = std::str::from_utf8(key.as_bytes())?;
+            if !["url", "name", "sha256"].contains(&key) {
+                return Err(ErrorKind::UnexpectedArgumentBuiltin(key.into()));
+            }
+
+            let value = generators::request_force(&co, value.clone()).await;
+            if let Value::Catchable(_) = value {
+                return Ok(value);
+            }
+            fields.insert(key, String::from_utf8(value.to_str()?.as_bytes().to_vec())?);
+        }
 
+        let url = fields
+            .remove("url")
+            .ok_or_else(|| ErrorKind::AttributeNotFound { name: "url".into() })?;
+        let sha256 = fields
+            .remove("sha256")
+            .ok_or(ErrorKind::UnsupportedByIO(FETCHURL_WITHOUT_HASH))?;
+        let name = fields
+            .remove("name")
+            .unwrap_or_else(|| url_basename(&url).to_owned());
+
+        let hash = nix_compat::nixhash::from_str(&sha256, Some("sha256"))
+            .map_err(|e| ErrorKind::InvalidHash(e.to_string()))?;
+        let store_path: StorePath<String> =
+            build_ca_path(&name, &CAHash::Flat(hash), Vec::<String>::new(), false)
+                .map_err(|e| ErrorKind::TvixError(Rc::new(e)))?;
+        let path = PathBuf::from(store_path.to_absolute_path());
+
+        match state.path_exists(&path) {
+            Ok(true) => Ok(Value::Path(Box::new(path))),
+            Ok(false) => Ok(Value::from(CatchableErrorKind::UnimplementedFeature(
+                format!("fetching {url}, {} is not available", path.display()).into(),
+            ))),
+            Err(error) => io_error_to_result(path, error),
+        }
+    }
+
+    // The fetchers below would need to fetch and unpack sources to determine their store paths,
+    // which the simulated store can't do. Say so, rather than suggesting Tvix lacks them.
     #[builtin("fetchGit")]
     async fn builtin_fetch_git(co: GenCo, args: Value) -> std::result::Result<Value, ErrorKind> {
-        Err(ErrorKind::NotImplemented("fetchGit"))
+        Err(ErrorKind::UnsupportedByIO(
+            "fetchGit requires a real store, unavailable in tvix-simstore",
+        ))
     }
 
     #[builtin("fetchMercurial")]
@@ -312,7 +873,9 @@ mod builtins {
         co: GenCo,
         args: Value,
     ) -> std::result::Result<Value, ErrorKind> {
-        Err(ErrorKind::NotImplemented("fetchMercurial"))
+        Err(ErrorKind::UnsupportedByIO(
+            "fetchMercurial requires a real store, unavailable in tvix-simstore",
+        ))
     }
 
     #[builtin("fetchTarball")]
@@ -320,12 +883,113 @@ mod builtins {
         co: GenCo,
         args: Value,
     ) -> std::result::Result<Value, ErrorKind> {
-        Err(ErrorKind::NotImplemented("fetchTarball"))
+        Err(ErrorKind::UnsupportedByIO(
+            "fetchTarball requires a real store, unavailable in tvix-simstore",
+        ))
+    }
+
+    /// Flakes are not supported. Failing catchably allows expressions to fall back to
+    /// something else using `builtins.tryEval`.
+    #[builtin("getFlake")]
+    async fn builtin_get_flake(
+        co: GenCo,
+        flake_ref: Value,
+    ) -> std::result::Result<Value, ErrorKind> {
+        Ok(Value::from(CatchableErrorKind::UnimplementedFeature(
+            "flakes".into(),
+        )))
+    }
+
+    #[builtin("pathExists")]
+    async fn builtin_path_exists(
+        state: Rc<dyn EvalIO>,
+        co: GenCo,
+        path: Value,
+    ) -> std::result::Result<Value, ErrorKind> {
+        let path = match coerce_value_to_path(&co, path).await? {
+            Err(cek) => return Ok(Value::from(cek)),
+            Ok(path) => path,
+        };
+
+        match state.path_exists(&path) {
+            Ok(exists) => Ok(Value::Bool(exists)),
+            // Unrealised store paths don't exist as far as the simulated store is concerned.
+            Err(error) if is_store_path_read(&error) => Ok(Value::Bool(false)),
+            Err(error) => io_error_to_result(path, error),
+        }
+    }
+
+    #[builtin("readFileType")]
+    async fn builtin_read_file_type(
+        state: Rc<dyn EvalIO>,
+        co: GenCo,
+        path: Value,
+    ) -> std::result::Result<Value, ErrorKind> {
+        let path = match coerce_value_to_path(&co, path).await? {
+            Err(cek) => return Ok(Value::from(cek)),
+            Ok(path) => path,
+        };
+
+        match state.file_type(&path) {
+            Ok(file_type) => Ok(Value::from(file_type.to_string())),
+            Err(error) => io_error_to_result(path, error),
+        }
+    }
+
+    #[builtin("readDir")]
+    async fn builtin_read_dir(
+        state: Rc<dyn EvalIO>,
+        co: GenCo,
+        path: Value,
+    ) -> std::result::Result<Value, ErrorKind> {
+        let path = match coerce_value_to_path(&co, path).await? {
+            Err(cek) => return Ok(Value::from(cek)),
+            Ok(path) => path,
+        };
+
+        match state.read_dir(&path) {
+            Ok(entries) => Ok(Value::attrs(NixAttrs::from_iter(entries.into_iter().map(
+                |(name, file_type)| (name.to_vec(), Value::from(file_type.to_string())),
+            )))),
+            Err(error) => io_error_to_result(path, error),
+        }
     }
 }
 
-pub fn simulated_store_builtins() -> Vec<(&'static str, Value)> {
-    builtins::builtins()
+/// Returns the builtins needed for evaluating with a simulated store. The given IO handle is
+/// expected to (eventually) dispatch to a [`SimulatedStoreIO`].
+pub fn simulated_store_builtins(io: Rc<dyn EvalIO>) -> Vec<(&'static str, Value)> {
+    builtins::builtins(io)
+}
+
+#[builtins(state = "Rc<HashSet<String>>")]
+mod env_builtins {
+    use super::*;
+    use tvix_eval::generators::{Gen, GenCo};
+
+    /// Like `builtins.getEnv`, but returns the empty string (as for unset variables) for all
+    /// variables which have not been allowed explicitly.
+    #[builtin("getEnv")]
+    async fn builtin_get_env(
+        state: Rc<HashSet<String>>,
+        co: GenCo,
+        var: Value,
+    ) -> std::result::Result<Value, ErrorKind> {
+        let var = var.to_str()?;
+        match std::str::from_utf8(var.as_bytes()) {
+            Ok(name) if state.contains(name) => Ok(std::env::var(name).unwrap_or_default().into()),
+            _ => Ok("".into()),
+        }
+    }
+}
+
+/// Returns a `builtins.getEnv` which only reads the environment variables allowed on the given
+/// store (see [`GenericSimulatedStoreIO::allow_env_var`]), making environment access auditable.
+/// Variables allowed after calling this are not taken into account.
+pub fn simulated_env_builtins<P: PassthruPaths>(
+    store: &GenericSimulatedStoreIO<P>,
+) -> Vec<(&'static str, Value)> {
+    env_builtins::builtins(Rc::new(store.allow_env_vars.clone()))
 }
 
 #[cfg(test)]
@@ -333,6 +997,11 @@ pub fn simulated_store_builtins() -> Vec<(&'static str, Value)> {
 mod tests {
     use super::*;
 
+    /// Location of the `test-data` fixtures, independent of the working directory.
+    fn test_data() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("test-data")
+    }
+
     fn io_err_to_simstore_err<T>(res: Result<T>) -> SimulatedStoreError {
         res.err()
             .expect("Store Path Read should fail")
@@ -378,28 +1047,210 @@ mod tests {
         }
     }
 
+    /// Fallback IO serving the real Nix store from the given directory, so that tests don't
+    /// depend on the contents of the local `/nix/store`.
+    struct RebasedStoreIO(PathBuf);
+
+    impl RebasedStoreIO {
+        fn rebase(&self, path: &Path) -> PathBuf {
+            match path.strip_prefix("/nix/store") {
+                Ok(relative) => self.0.join(relative),
+                Err(_) => path.to_owned(),
+            }
+        }
+    }
+
+    impl EvalIO for RebasedStoreIO {
+        fn import_path(&self, path: &Path) -> Result<PathBuf> {
+            StdIO.import_path(&self.rebase(path))
+        }
+
+        fn path_exists(&self, path: &Path) -> Result<bool> {
+            StdIO.path_exists(&self.rebase(path))
+        }
+
+        fn open(&self, path: &Path) -> Result<Box<dyn Read>> {
+            StdIO.open(&self.rebase(path))
+        }
+
+        fn file_type(&self, path: &Path) -> Result<FileType> {
+            StdIO.file_type(&self.rebase(path))
+        }
+
+        fn read_dir(&self, path: &Path) -> Result<Vec<(bytes::Bytes, FileType)>> {
+            StdIO.read_dir(&self.rebase(path))
+        }
+    }
+
+    #[test]
+    fn real_store_read_forbidden() {
+        let real_store = tempfile::tempdir().expect("must be able to create directory");
+        fs::create_dir(
+            real_store
+                .path()
+                .join("a396z42saqql55cp5n1vrb2j0siq86k1-nixpkgs-src"),
+        )
+        .expect("must be able to create store path");
+        let real_path = Path::new("/nix/store/a396z42saqql55cp5n1vrb2j0siq86k1-nixpkgs-src");
+
+        let mut store_io = SimulatedStoreIO::default()
+            .with_fallback_io(Box::new(RebasedStoreIO(real_store.path().to_owned())));
+        assert!(
+            store_io
+                .path_exists(real_path)
+                .expect("real store paths should be readable by default")
+        );
+
+        store_io.forbid_real_store(true);
+        assert_eq!(
+            io_err_to_simstore_err(store_io.path_exists(real_path)),
+            SimulatedStoreError::StorePathRead
+        );
+        assert_eq!(
+            io_err_to_simstore_err(store_io.file_type(real_path)),
+            SimulatedStoreError::StorePathRead
+        );
+    }
+
     #[test]
     fn imported_paths() {
         let store_io = SimulatedStoreIO::default();
         assert_eq!(
             store_io
-                .import_path(Path::new("./test-data/q.txt"))
+                .import_path(&test_data().join("q.txt"))
                 .expect("importing test data should succeed"),
             Path::new("/nix/store/6w97x3p5yw17nwvqn3s6mrhdlznmzmiv-q.txt")
         );
         assert_eq!(
             store_io
-                .import_path(Path::new("./test-data"))
+                .import_path(&test_data())
                 .expect("importing test data should succeed"),
             Path::new("/nix/store/ljqm0pf4b43bk53lymzrbljvdxi5vkcm-test-data")
         );
     }
 
+    #[test]
+    fn import_max_depth() {
+        let mut store_io = SimulatedStoreIO::default();
+        store_io.set_import_options(ImportOptions {
+            max_depth: Some(0),
+            ..Default::default()
+        });
+
+        let err = store_io
+            .import_path(&test_data())
+            .expect_err("importing a tree exceeding max_depth should fail");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(
+            err.to_string()
+                .contains("exceeds the maximum import depth of 0"),
+            "unexpected error: {err}"
+        );
+
+        store_io.set_import_options(ImportOptions {
+            max_depth: Some(1),
+            max_open: 1,
+            ..Default::default()
+        });
+        assert_eq!(
+            store_io
+                .import_path(&test_data())
+                .expect("importing a tree within max_depth should succeed"),
+            Path::new("/nix/store/ljqm0pf4b43bk53lymzrbljvdxi5vkcm-test-data")
+        );
+    }
+
+    #[test]
+    fn import_scan_references() {
+        let tmp = std::env::temp_dir().join(format!("tvix-simstore-refs-{}", std::process::id()));
+        let root = tmp.join("script");
+        fs::create_dir_all(&tmp).expect("must be able to create directory");
+        fs::write(
+            &root,
+            "#!/nix/store/00bgd045z0d4icpbc2yyz4gx48ak44la-bash-5.2/bin/bash\n\
+             echo /nix/store/not-a-store-path\n",
+        )
+        .expect("must be able to write file");
+
+        let mut store_io = SimulatedStoreIO::default();
+        let unscanned = store_io.import_path_with_references(&root);
+        store_io.set_import_options(ImportOptions {
+            scan_references: true,
+            ..Default::default()
+        });
+        let scanned = store_io.import_path_with_references(&root);
+        fs::remove_dir_all(&tmp).expect("must be able to clean up");
+
+        let (path, references) = unscanned.expect("importing should succeed");
+        assert!(references.is_none());
+
+        let (scanned_path, references) = scanned.expect("importing should succeed");
+        assert_eq!(path, scanned_path);
+        assert_eq!(
+            references.expect("references must be scanned"),
+            vec![
+                StorePath::<String>::from_bytes(b"00bgd045z0d4icpbc2yyz4gx48ak44la-bash-5.2")
+                    .unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn store_path_collector_split_writes() {
+        let data = b"foo /nix/store/00bgd045z0d4icpbc2yyz4gx48ak44la-bash-5.2/bin/sh bar\
+                     /nix/store/00bgd045z0d4icpbc2yyz4gx48ak44la-bash-5.2 \
+                     /nix/store/10bgd045z0d4icpbc2yyz4gx48ak44la-coreutils";
+
+        // Writing one byte at a time must find the same references as a single write.
+        let mut collector = StorePathCollector::new(std::io::sink(), "/nix/store");
+        for b in data {
+            collector.write_all(&[*b]).expect("must succeed");
+        }
+        let references = collector.finish();
+
+        let mut collector = StorePathCollector::new(std::io::sink(), "/nix/store");
+        collector.write_all(data).expect("must succeed");
+        assert_eq!(references, collector.finish());
+
+        assert_eq!(
+            references
+                .iter()
+                .map(|store_path| store_path.to_absolute_path())
+                .collect::<Vec<_>>(),
+            vec![
+                "/nix/store/00bgd045z0d4icpbc2yyz4gx48ak44la-bash-5.2",
+                "/nix/store/10bgd045z0d4icpbc2yyz4gx48ak44la-coreutils",
+            ]
+        );
+    }
+
+    #[test]
+    fn import_non_utf8_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let tmp = std::env::temp_dir().join(format!("tvix-simstore-{}", std::process::id()));
+        let root = tmp.join("non-utf8");
+        fs::create_dir_all(&root).expect("must be able to create directory");
+        fs::write(root.join(OsStr::from_bytes(b"file-\xff")), "hello")
+            .expect("must be able to write file");
+        std::os::unix::fs::symlink(OsStr::from_bytes(b"target-\xfe\xff"), root.join("link"))
+            .expect("must be able to create symlink");
+
+        let imported = SimulatedStoreIO::default().import_path(&root);
+        fs::remove_dir_all(&tmp).expect("must be able to clean up");
+
+        assert_eq!(
+            imported.expect("importing non-UTF8 names should succeed"),
+            Path::new("/nix/store/56ib01jiy73q3r7njiqih34fgx5wa5ki-non-utf8")
+        );
+    }
+
     #[test]
     fn passthru_paths_file() {
         let store_io = SimulatedStoreIO::default();
         let imported = store_io
-            .import_path(Path::new("./test-data/q.txt"))
+            .import_path(&test_data().join("q.txt"))
             .expect("importing test data should succeed");
         assert!(
             store_io
@@ -412,7 +1263,7 @@ mod tests {
     fn passthru_paths_folder() {
         let store_io = SimulatedStoreIO::default();
         let imported = store_io
-            .import_path(Path::new("./test-data"))
+            .import_path(&test_data())
             .expect("importing test data should succeed");
         assert!(
             store_io
@@ -421,6 +1272,311 @@ mod tests {
         );
     }
 
+    #[test]
+    fn import_flat_expected_hash() {
+        let import = |expected: &str| {
+            let expected = nix_compat::nixhash::from_str(expected, Some("sha256"))
+                .expect("expected hash must be valid");
+            let entries = walkdir::WalkDir::new(test_data().join("q.txt")).into_iter();
+
+            SimulatedStoreIO::default().import_path_by_entries(
+                "q.txt",
+                entries,
+                Some(CAHash::Flat(expected)),
+            )
+        };
+
+        assert_eq!(
+            import("1br2arkcnlf0k8bhslp0h6adxhi7nnxa739vwq92dyfssziagh61")
+                .expect("importing with the correct hash should succeed")
+                .to_absolute_path(),
+            "/nix/store/hwssciv12d4h0vawapirxipl14gs0g3c-q.txt"
+        );
+
+        import("0000000000000000000000000000000000000000000000000000")
+            .expect_err("importing with a mismatching hash should fail");
+    }
+
+    #[test]
+    fn import_flat_expected_hash_directory() {
+        let expected = CAHash::Flat(NixHash::Sha256([0; 32]));
+        let entries = walkdir::WalkDir::new(test_data()).into_iter();
+
+        let err = SimulatedStoreIO::default()
+            .import_path_by_entries("test-data", entries, Some(expected))
+            .expect_err("flat imports of directories should fail");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    fn eval(io: Rc<SimulatedStoreIO>, code: &str) -> tvix_eval::EvaluationResult {
+        tvix_eval::Evaluation::builder(io.clone() as Rc<dyn EvalIO>)
+            .add_builtins(simulated_store_builtins(io))
+            .build()
+            .evaluate(code, None)
+    }
+
+    #[test]
+    fn read_file_type() {
+        let store_io = Rc::new(SimulatedStoreIO::default());
+        let test_data = test_data();
+
+        for (path, expected) in [
+            (test_data.clone(), "directory"),
+            (test_data.join("q.txt"), "regular"),
+        ] {
+            let result = eval(
+                store_io.clone(),
+                &format!("builtins.readFileType {}", path.display()),
+            );
+            assert!(result.errors.is_empty(), "{:?}", result.errors);
+            assert_eq!(
+                result.value.expect("must be some").to_str().unwrap(),
+                expected
+            );
+        }
+
+        // Imported paths are passed through to their original location.
+        let result = eval(
+            store_io,
+            &format!(
+                r#"builtins.readFileType "${{{}}}/q.txt""#,
+                test_data.display()
+            ),
+        );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(
+            result.value.expect("must be some").to_str().unwrap(),
+            "regular"
+        );
+    }
+
+    #[test]
+    fn read_dir() {
+        let test_data = test_data();
+        let result = eval(
+            Rc::new(SimulatedStoreIO::default()),
+            &format!(r#"(builtins.readDir {})."q.txt""#, test_data.display()),
+        );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(
+            result.value.expect("must be some").to_str().unwrap(),
+            "regular"
+        );
+
+        let result = eval(
+            Rc::new(SimulatedStoreIO::default()),
+            r#"(builtins.tryEval (builtins.readDir /nix/store/n2v8qwc85kl4nk6ipfpaxs2pkjzka4v8-serve-examples)).success"#,
+        );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert!(matches!(result.value, Some(Value::Bool(false))));
+    }
+
+    #[test]
+    fn get_flake() {
+        let result = eval(
+            Rc::new(SimulatedStoreIO::default()),
+            r#"(builtins.tryEval (builtins.getFlake "github:NixOS/nixpkgs")).success"#,
+        );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert!(matches!(result.value, Some(Value::Bool(false))));
+    }
+
+    #[test]
+    fn fetchers_unavailable() {
+        for (code, message) in [
+            (
+                r#"builtins.fetchGit { url = "https://example.com/repo.git"; }"#,
+                "fetchGit requires a real store, unavailable in tvix-simstore",
+            ),
+            (
+                r#"builtins.fetchTarball "https://example.com/src.tar.gz""#,
+                "fetchTarball requires a real store, unavailable in tvix-simstore",
+            ),
+            (
+                r#"builtins.fetchurl { url = "https://example.com/file"; }"#,
+                FETCHURL_WITHOUT_HASH,
+            ),
+            (
+                r#"builtins.fetchurl "https://example.com/file""#,
+                FETCHURL_WITHOUT_HASH,
+            ),
+        ] {
+            let result = eval(Rc::new(SimulatedStoreIO::default()), code);
+            assert!(result.value.is_none());
+            assert_eq!(result.errors.len(), 1);
+
+            let error = &result.errors[0];
+            assert_eq!(error.to_diagnostic().code, "E047", "{code}");
+            assert!(
+                error.to_string().contains(message) && !error.to_string().contains("not yet"),
+                "unexpected error for {code}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn path_exists() {
+        let test_data = test_data();
+
+        for (path, expected) in [
+            (test_data.join("q.txt"), true),
+            (test_data.join("missing.txt"), false),
+            (
+                PathBuf::from("/nix/store/n2v8qwc85kl4nk6ipfpaxs2pkjzka4v8-serve-examples"),
+                false,
+            ),
+        ] {
+            let result = eval(
+                Rc::new(SimulatedStoreIO::default()),
+                &format!("builtins.pathExists {}", path.display()),
+            );
+            assert!(result.errors.is_empty(), "{:?}", result.errors);
+            assert!(
+                matches!(result.value, Some(Value::Bool(b)) if b == expected),
+                "pathExists {} should be {expected}",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn read_file_type_store_path_catchable() {
+        let result = eval(
+            Rc::new(SimulatedStoreIO::default()),
+            r#"(builtins.tryEval (builtins.readFileType /nix/store/n2v8qwc85kl4nk6ipfpaxs2pkjzka4v8-serve-examples)).success"#,
+        );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert!(matches!(result.value, Some(Value::Bool(false))));
+    }
+
+    #[test]
+    fn fetchurl_passthru() {
+        const FETCHURL: &str = r#"builtins.fetchurl {
+          url = "https://example.com/q.txt";
+          sha256 = "1br2arkcnlf0k8bhslp0h6adxhi7nnxa739vwq92dyfssziagh61";
+        }"#;
+        let expected = "/nix/store/hwssciv12d4h0vawapirxipl14gs0g3c-q.txt";
+
+        // Without the file being available, fetching fails.
+        let result = eval(
+            Rc::new(SimulatedStoreIO::default()),
+            &format!("(builtins.tryEval ({FETCHURL})).success"),
+        );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert!(matches!(result.value, Some(Value::Bool(false))));
+
+        let mut store_io = SimulatedStoreIO::default();
+        store_io
+            .add_passthru(expected, test_data().join("q.txt"))
+            .expect("adding passthru should work");
+
+        let result = eval(Rc::new(store_io), FETCHURL);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert!(
+            matches!(&result.value, Some(Value::Path(p)) if p.as_path() == Path::new(expected)),
+            "unexpected value: {:?}",
+            result.value
+        );
+    }
+
+    #[test]
+    fn get_env_allowlist() {
+        let mut store_io = SimulatedStoreIO::default();
+        store_io.allow_env_var("CARGO_PKG_NAME");
+
+        let eval_get_env = |name: &str| {
+            let result = tvix_eval::Evaluation::builder_pure()
+                .add_builtins(simulated_env_builtins(&store_io))
+                .build()
+                .evaluate(format!(r#"builtins.getEnv "{name}""#), None);
+            assert!(result.errors.is_empty(), "{:?}", result.errors);
+            result.value.expect("must be some").to_str().unwrap()
+        };
+
+        assert_eq!(
+            eval_get_env("CARGO_PKG_NAME"),
+            std::env::var("CARGO_PKG_NAME").unwrap().as_str()
+        );
+
+        // Variables which are set, but not allowed, appear to be unset.
+        assert!(std::env::var("CARGO_MANIFEST_DIR").is_ok());
+        assert_eq!(eval_get_env("CARGO_MANIFEST_DIR"), "");
+    }
+
+    #[test]
+    fn shared_store_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedSimulatedStoreIO>();
+
+        let store_io = SharedSimulatedStoreIO::default();
+
+        let imported = std::thread::spawn({
+            let store_io = store_io.clone();
+            move || {
+                store_io
+                    .import_path(&test_data().join("q.txt"))
+                    .expect("importing test data should succeed")
+            }
+        })
+        .join()
+        .expect("importing thread should not panic");
+
+        // The passthru path registered by the other thread is visible here.
+        assert!(
+            store_io
+                .path_exists(&imported)
+                .expect("imported path should be forwarded")
+        );
+
+        let other = store_io.clone();
+        std::thread::spawn(move || {
+            assert!(
+                other
+                    .path_exists(&imported)
+                    .expect("imported path should be forwarded")
+            );
+        })
+        .join()
+        .expect("reading thread should not panic");
+    }
+
+    #[test]
+    fn recorded_operations() {
+        let store_io = Rc::new(RecordingStoreIO::new(SimulatedStoreIO::default()));
+        let test_data = test_data();
+
+        let result = tvix_eval::Evaluation::builder(store_io.clone() as Rc<dyn EvalIO>)
+            .add_builtins(tvix_eval::builtins::impure_builtins())
+            .build()
+            .evaluate(
+                format!(r#"builtins.readFile "${{{}}}/q.txt""#, test_data.display()),
+                None,
+            );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+        assert_eq!(
+            store_io.operations(),
+            vec![
+                IoOp::ImportPath(test_data),
+                IoOp::Open(PathBuf::from(
+                    "/nix/store/ljqm0pf4b43bk53lymzrbljvdxi5vkcm-test-data/q.txt"
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn pure_expression_performs_no_operations() {
+        let store_io = Rc::new(RecordingStoreIO::new(SimulatedStoreIO::default()));
+
+        let result = tvix_eval::Evaluation::builder(store_io.clone() as Rc<dyn EvalIO>)
+            .build()
+            .evaluate("builtins.toJSON { a = 1 + 1; }", None);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+
+        assert_eq!(store_io.operations(), vec![]);
+    }
+
     #[test]
     fn added_passthru_path() {
         let mut store_io = SimulatedStoreIO::default();
@@ -447,4 +1603,110 @@ mod tests {
             false
         )
     }
+
+    #[test]
+    fn passthru_manifest() {
+        let dir = tempfile::tempdir().expect("creating a tempdir should work");
+        let example = "/nix/store/a396z42saqql55cp5n1vrb2j0siq86k1-nixpkgs-src";
+
+        for (name, manifest) in [
+            (
+                "passthru.json",
+                format!(r#"{{ "{example}": "{}" }}"#, test_data().display()),
+            ),
+            (
+                "passthru.toml",
+                format!(r#""{example}" = "{}""#, test_data().display()),
+            ),
+        ] {
+            let manifest_path = dir.path().join(name);
+            fs::write(&manifest_path, manifest).expect("writing the manifest should work");
+
+            let mut store_io = SimulatedStoreIO::default();
+            store_io
+                .load_passthru_manifest(&manifest_path)
+                .expect("loading the manifest should work");
+
+            assert!(
+                store_io
+                    .path_exists(&Path::new(example).join("q.txt"))
+                    .expect("mapped path should be forwarded"),
+                "{name}"
+            );
+        }
+
+        let invalid = dir.path().join("invalid.json");
+        fs::write(&invalid, r#"{ "/tmp/not-a-store-path": "/tmp" }"#)
+            .expect("writing the manifest should work");
+        SimulatedStoreIO::default()
+            .load_passthru_manifest(&invalid)
+            .expect_err("invalid store paths should be rejected");
+    }
+
+    /// Fallback IO recording its operations in a [`RecordingStoreIO`] shared with the test.
+    struct SharedRecordingIO(Rc<RecordingStoreIO<StdIO>>);
+
+    impl EvalIO for SharedRecordingIO {
+        fn import_path(&self, path: &Path) -> Result<PathBuf> {
+            self.0.import_path(path)
+        }
+
+        fn path_exists(&self, path: &Path) -> Result<bool> {
+            self.0.path_exists(path)
+        }
+
+        fn open(&self, path: &Path) -> Result<Box<dyn Read>> {
+            self.0.open(path)
+        }
+
+        fn file_type(&self, path: &Path) -> Result<FileType> {
+            self.0.file_type(path)
+        }
+
+        fn read_dir(&self, path: &Path) -> Result<Vec<(bytes::Bytes, FileType)>> {
+            self.0.read_dir(path)
+        }
+    }
+
+    #[test]
+    fn fallback_io() {
+        let recording = Rc::new(RecordingStoreIO::new(StdIO));
+        let store_io = SimulatedStoreIO::default()
+            .with_fallback_io(Box::new(SharedRecordingIO(recording.clone())));
+
+        let file = test_data().join("q.txt");
+        assert!(store_io.path_exists(&file).expect("reading should work"));
+        store_io
+            .file_type(&test_data())
+            .expect("reading should work");
+
+        // Reads from unknown store paths check the real store through the fallback.
+        let store_path = Path::new("/nix/store/a396z42saqql55cp5n1vrb2j0siq86k1-nixpkgs-src");
+        io_err_to_simstore_err(store_io.read_dir(store_path));
+
+        assert_eq!(
+            recording.operations(),
+            vec![
+                IoOp::PathExists(file),
+                IoOp::FileType(test_data()),
+                IoOp::PathExists(store_path.to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn is_store_path() {
+        let store_io = SimulatedStoreIO::default();
+        let example = Path::new("/nix/store/a396z42saqql55cp5n1vrb2j0siq86k1-nixpkgs-src");
+
+        assert!(store_io.is_store_path(example));
+        assert!(store_io.is_store_path(&example.join("lib/default.nix")));
+
+        assert!(!store_io.is_store_path(&test_data()));
+        assert!(!store_io.is_store_path(Path::new("/nix/storefoo")));
+        assert!(!store_io.is_store_path(Path::new("/nix")));
+
+        assert!(!store_io.is_store_path(Path::new("/nix/store")));
+        assert!(!store_io.is_store_path(Path::new("/nix/store/")));
+    }
 }
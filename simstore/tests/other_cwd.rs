@@ -0,0 +1,27 @@
+//! Fixture paths must resolve regardless of the working directory tests are run from.
+//!
+//! Changing the working directory affects the whole process, so this lives in its own test
+//! binary, which must not contain any other tests.
+
+use std::path::Path;
+
+use tvix_eval::EvalIO;
+use tvix_simstore::SimulatedStoreIO;
+
+#[test]
+fn imported_paths_other_cwd() {
+    std::env::set_current_dir(std::env::temp_dir()).expect("must be able to change directory");
+
+    // Relative fixture paths are resolved against the working directory.
+    SimulatedStoreIO::default()
+        .import_path(Path::new("./test-data"))
+        .expect_err("relative fixture paths must not resolve outside the crate");
+
+    let test_data = Path::new(env!("CARGO_MANIFEST_DIR")).join("test-data");
+    assert_eq!(
+        SimulatedStoreIO::default()
+            .import_path(&test_data)
+            .expect("importing test data should succeed"),
+        Path::new("/nix/store/ljqm0pf4b43bk53lymzrbljvdxi5vkcm-test-data")
+    );
+}